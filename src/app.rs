@@ -1,10 +1,20 @@
 use crate::data::Data;
 #[cfg(feature = "kalc-lib")]
-use crate::data::init;
+use crate::data::{domain, init, join_function};
 use crate::{App, get_names};
 #[cfg(feature = "kalc-lib")]
 use crate::{C, F, I};
+#[cfg(feature = "kalc-lib")]
+use kalc_lib::units::{Options, Variable};
 use rupl::types::Graph;
+#[cfg(feature = "kalc-lib")]
+pub(crate) fn tab_complete(
+    vars: Vec<Variable<I, F, C>>,
+    options: Options,
+) -> Option<Box<dyn Fn(&str) -> Vec<String>>> {
+    let word = move |w: &str| -> Vec<String> { kalc_lib::misc::get_word_bank(w, &vars, options) };
+    Some(Box::new(word) as Box<dyn Fn(&str) -> Vec<String>>)
+}
 impl App {
     #[cfg(feature = "kalc-lib")]
     pub(crate) fn new(function: String, data: kalc_lib::units::Data<I, F, C>) -> Self {
@@ -21,31 +31,30 @@ impl App {
             vars,
             colors,
         } = data;
-        let mut side = false;
-        let (data, names, graphing_mode) =
-            if let Ok(a) = init(&function, &mut options, vars.clone()) {
-                if a.0.iter().any(|a| a.is_none()) {
-                    side = true
-                }
-                a
-            } else {
-                side = true;
-                Default::default()
-            };
-        let tab_complete = {
-            let vars = vars.clone();
-            let word =
-                move |w: &str| -> Vec<String> { kalc_lib::misc::get_word_bank(w, &vars, options) };
-            Some(Box::new(word) as Box<dyn Fn(&str) -> Vec<String>>)
-        };
+        let (functions, graphing_mode) = init(&function, &mut options, vars.clone());
+        let side = functions.iter().any(|a| a.graph_type.is_none());
+        let tab_complete = tab_complete(vars.clone(), options);
         let mut data = Data {
-            data,
+            data: Vec::new(),
+            errors: Vec::new(),
+            deep: None,
+            floor: false,
+            shown: Vec::new(),
+            sources: Vec::new(),
+            changes: None,
+            ruler: None,
+            domain: domain(options.graphtype),
             options,
             vars,
             blacklist: Vec::new(),
             var: rupl::types::Vec2::new(options.xr.0, options.xr.1),
             count_changed: false,
+            refine: None,
+            budget: Default::default(),
+            #[cfg(feature = "bincode")]
+            session: None,
         };
+        let names = data.set_functions(functions);
         let mut graph = Vec::new();
         let complex = if graphing_mode.x && graphing_mode.y {
             data.generate_3d(
@@ -83,11 +92,6 @@ impl App {
         let b = side;
         let mut plot = Graph::new(graph, names, complex, options.xr.0, options.xr.1);
         plot.tab_complete = tab_complete;
-        #[cfg(feature = "bincode")]
-        {
-            plot.save_file =
-                dirs::config_dir().unwrap().to_str().unwrap().to_owned() + "/kalc/plot";
-        }
         if b {
             plot.menu = rupl::types::Menu::Side;
             plot.text_box = Some((0, 0));
@@ -146,6 +150,17 @@ impl App {
             dpr: 1.0,
             #[cfg(feature = "wasm")]
             window: None,
+            #[cfg(feature = "bincode")]
+            session: None,
+            #[cfg(feature = "bincode")]
+            commit: false,
+            #[cfg(feature = "bincode")]
+            live: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            control: None,
+            #[cfg(feature = "bincode")]
+            #[cfg(any(feature = "skia", feature = "tiny-skia", feature = "wasm-draw"))]
+            recorder: None,
         }
     }
     #[cfg(not(feature = "kalc-lib"))]
@@ -163,11 +178,16 @@ impl App {
                     },
                     inv: None,
                 },
+                cost: Default::default(),
+                limit: Default::default(),
+                sampling: Default::default(),
             })],
             blacklist: Vec::new(),
             options,
             var: rupl::types::Vec2::new(options.xr.0, options.xr.1),
             count_changed: false,
+            refine: None,
+            budget: Default::default(),
         };
         let mut graph = Vec::new();
         let complex = data.generate_3d(
@@ -183,11 +203,6 @@ impl App {
         let names = &[(Vec::new(), "sin(1/z)".to_string())];
         let names = get_names(&graph, names);
         let mut plot = Graph::new(graph, names, complex, options.xr.0, options.xr.1);
-        #[cfg(feature = "bincode")]
-        {
-            plot.save_file =
-                dirs::config_dir().unwrap().to_str().unwrap().to_owned() + "/kalc/plot";
-        }
         plot.is_complex = complex;
         plot.mult = 1.0 / 16.0;
         data.update(&mut plot);
@@ -213,6 +228,28 @@ impl App {
             dpr: 1.0,
         }
     }
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) fn title(&self) -> String {
+        join_function(&self.plot.names, &self.plot.blacklist_graphs)
+    }
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) fn replace_plot(&mut self, mut plot: Graph) {
+        #[cfg(feature = "skia-vulkan")]
+        {
+            plot.renderer = self.plot.renderer.take();
+        }
+        plot.tab_complete = tab_complete(self.data.vars.clone(), self.data.options);
+        self.plot = plot;
+    }
+    pub(crate) fn update_data(&mut self) -> Option<String> {
+        let ret = self.data.update(&mut self.plot);
+        #[cfg(feature = "bincode")]
+        #[cfg(feature = "kalc-lib")]
+        if let Some(n) = self.commit_session() {
+            return Some(n);
+        }
+        ret
+    }
     #[cfg(feature = "egui")]
     pub(crate) fn main(&mut self, ctx: &egui::Context) {
         egui::CentralPanel::default()
@@ -226,16 +263,191 @@ impl App {
                 if let Some(tiny) = std::mem::take(&mut self.tiny) {
                     self.plot.apply_tiny(tiny);
                 }
-                if let Some(n) = self.data.update(&mut self.plot) {
+                #[cfg(feature = "bincode")]
+                #[cfg(feature = "kalc-lib")]
+                {
+                    let (n, close) = self.poll_live();
+                    if close {
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Close)
+                    }
+                    if let Some(n) = n {
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Title(n))
+                    }
+                }
+                #[cfg(feature = "kalc-lib")]
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    let (n, close) = self.poll_control();
+                    if close {
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Close)
+                    }
+                    if let Some(n) = n {
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Title(n))
+                    }
+                }
+                #[cfg(feature = "bincode")]
+                #[cfg(feature = "kalc-lib")]
+                {
+                    self.commit |= ctx.input(|i| i.key_pressed(egui::Key::Enter));
+                }
+                if let Some(n) = self.update_data() {
                     ctx.send_viewport_cmd(egui::ViewportCommand::Title(n))
                 }
+                if self.data.pending() {
+                    ctx.request_repaint()
+                }
                 self.plot.update(ctx, ui);
             });
+        #[cfg(feature = "kalc-lib")]
+        self.show_errors(ctx);
+        #[cfg(feature = "kalc-lib")]
+        self.show_precision(ctx);
+        #[cfg(feature = "bincode")]
+        #[cfg(feature = "kalc-lib")]
+        self.show_session_hint(ctx);
+        #[cfg(feature = "kalc-lib")]
+        self.show_orbits(ctx);
+        #[cfg(feature = "kalc-lib")]
+        self.show_analysis(ctx);
+    }
+    //winding numbers of path images and the zeros and poles found while domain colouring
+    #[cfg(feature = "egui")]
+    #[cfg(feature = "kalc-lib")]
+    fn show_analysis(&self, ctx: &egui::Context) {
+        let windings = self.data.windings();
+        let singularities = self.data.singularities();
+        if windings.is_empty() && singularities.is_empty() {
+            return;
+        }
+        let text = |s: String| egui::RichText::new(s).monospace();
+        egui::Area::new(egui::Id::new("analysis"))
+            .anchor(egui::Align2::LEFT_TOP, egui::vec2(8.0, 8.0))
+            .show(ctx, |ui| {
+                for (i, winding) in windings {
+                    let winding = crate::path::turns(winding);
+                    ui.label(text(format!("winding of {} about 0: {winding}", i + 1)));
+                }
+                for (i, found) in singularities {
+                    for s in found {
+                        ui.label(text(format!("{} {}", i + 1, s.label())));
+                    }
+                }
+            });
+    }
+    #[cfg(feature = "egui")]
+    #[cfg(feature = "kalc-lib")]
+    fn show_orbits(&self, ctx: &egui::Context) {
+        let orbits = self.data.orbits();
+        if orbits.is_empty() {
+            return;
+        }
+        let text = |s: String| egui::RichText::new(s).monospace();
+        egui::Area::new(egui::Id::new("orbits"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-8.0, -8.0))
+            .show(ctx, |ui| {
+                ui.horizontal_top(|ui| {
+                    for (i, orbit) in orbits {
+                        egui::Grid::new(("orbit", i)).striped(true).show(ui, |ui| {
+                            ui.label(text("n".to_owned()));
+                            ui.label(text(format!("x_n of {}", i + 1)));
+                            ui.end_row();
+                            for (n, x) in orbit.iter().enumerate() {
+                                ui.label(text(n.to_string()));
+                                ui.label(text(format!("{x:.6}")));
+                                ui.end_row();
+                            }
+                        });
+                    }
+                });
+            });
+    }
+    #[cfg(feature = "egui")]
+    #[cfg(feature = "bincode")]
+    #[cfg(feature = "kalc-lib")]
+    fn show_session_hint(&self, ctx: &egui::Context) {
+        let Some(hint) = self.session_hint() else {
+            return;
+        };
+        egui::Area::new(egui::Id::new("sessions"))
+            .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -8.0))
+            .show(ctx, |ui| {
+                ui.label(egui::RichText::new(hint).monospace());
+            });
     }
+    #[cfg(feature = "egui")]
+    #[cfg(feature = "kalc-lib")]
+    fn show_precision(&self, ctx: &egui::Context) {
+        let Some(precision) = self.data.precision() else {
+            return;
+        };
+        egui::Area::new(egui::Id::new("precision"))
+            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-8.0, 8.0))
+            .show(ctx, |ui| {
+                ui.label(
+                    egui::RichText::new(precision)
+                        .monospace()
+                        .color(egui::Color32::from_rgb(200, 120, 0)),
+                );
+            });
+    }
+    #[cfg(feature = "egui")]
+    #[cfg(feature = "kalc-lib")]
+    fn show_errors(&self, ctx: &egui::Context) {
+        if self.data.errors.iter().all(|e| e.is_none()) {
+            return;
+        }
+        let format = |color, background| egui::TextFormat {
+            font_id: egui::FontId::monospace(14.0),
+            color,
+            background,
+            ..Default::default()
+        };
+        let black = egui::Color32::from_rgb(0, 0, 0);
+        let red = egui::Color32::from_rgb(200, 0, 0);
+        let none = egui::Color32::TRANSPARENT;
+        egui::Area::new(egui::Id::new("errors"))
+            .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(8.0, -8.0))
+            .show(ctx, |ui| {
+                for error in self.data.errors.iter().flatten() {
+                    let mut job = egui::text::LayoutJob::default();
+                    let chars = error.function.chars().collect::<Vec<char>>();
+                    if let Some(p) = error.position.filter(|p| *p < chars.len()) {
+                        let text = |r: std::ops::Range<usize>| chars[r].iter().collect::<String>();
+                        job.append(&text(0..p), 0.0, format(black, none));
+                        job.append(&text(p..p + 1), 0.0, format(black, red));
+                        job.append(&text(p + 1..chars.len()), 0.0, format(black, none));
+                    } else {
+                        job.append(&error.function, 0.0, format(black, none));
+                    }
+                    job.append(&format!("  {}", error.message), 0.0, format(red, none));
+                    ui.label(job);
+                }
+            });
+    }
+    #[cfg(feature = "kalc-lib")]
     #[cfg(any(feature = "skia", feature = "tiny-skia"))]
-    #[cfg(feature = "skia-vulkan")]
-    pub(crate) fn main(&mut self, width: u32, height: u32) {
-        let mut b = false;
+    pub(crate) fn png(&mut self, width: u32, height: u32) -> Vec<u8> {
+        while self.data.restore(&mut self.plot) || self.data.refine(&mut self.plot) {}
+        self.data.settle(&mut self.plot);
+        #[cfg(feature = "skia")]
+        let bytes = self.plot.get_png(width, height);
+        #[cfg(feature = "tiny-skia")]
+        let bytes = &self.plot.get_png(width, height);
+        bytes.as_bytes().to_vec()
+    }
+    #[cfg(feature = "kalc-lib")]
+    #[cfg(any(feature = "skia", feature = "tiny-skia"))]
+    pub(crate) fn render_png(&mut self, width: u32, height: u32) -> Vec<u8> {
+        self.plot
+            .set_screen(width as f64, height as f64, true, true);
+        self.plot.mult = 1.0;
+        self.plot.disable_lines = true;
+        self.plot.disable_axis = true;
+        self.data.update(&mut self.plot);
+        self.png(width, height)
+    }
+    #[cfg(any(feature = "skia", feature = "tiny-skia", feature = "wasm-draw"))]
+    pub(crate) fn prepare(&mut self, width: u32, height: u32) -> bool {
         self.plot.keybinds(&self.input_state);
         self.plot
             .set_screen(width as f64, height as f64, true, true);
@@ -243,10 +455,16 @@ impl App {
         if let Some(tiny) = std::mem::take(&mut self.tiny) {
             self.plot.apply_tiny(tiny);
         }
-        if let Some(n) = self.data.update(&mut self.plot) {
-            b = true;
+        let title = self.window_title();
+        if let Some(n) = self.update_data() {
             self.name = n;
-        };
+        }
+        title != self.window_title()
+    }
+    #[cfg(any(feature = "skia", feature = "tiny-skia"))]
+    #[cfg(feature = "skia-vulkan")]
+    pub(crate) fn main(&mut self, width: u32, height: u32) {
+        let b = self.prepare(width, height);
         self.plot.update();
         if b && let Some(w) = &self.plot.renderer {
             self.set_title(&w.window);
@@ -257,17 +475,10 @@ impl App {
     #[cfg(not(feature = "wasm"))]
     pub(crate) fn main(&mut self, width: u32, height: u32) {
         let mut b = false;
-        if let Some(buffer) = &mut self.surface_state {
-            self.plot.keybinds(&self.input_state);
-            self.plot
-                .set_screen(width as f64, height as f64, true, true);
-            #[cfg(feature = "bincode")]
-            if let Some(tiny) = std::mem::take(&mut self.tiny) {
-                self.plot.apply_tiny(tiny);
-            }
-            if let Some(n) = self.data.update(&mut self.plot) {
-                b = true;
-                self.name = n;
+        if self.surface_state.is_some() {
+            b = self.prepare(width, height);
+            let Some(buffer) = &mut self.surface_state else {
+                unreachable!()
             };
             let mut buffer = buffer.buffer_mut().unwrap();
             #[cfg(not(feature = "tiny-skia"))]
@@ -286,18 +497,7 @@ impl App {
     #[cfg(not(feature = "skia-vulkan"))]
     #[cfg(feature = "wasm")]
     pub(crate) fn main(&mut self, width: u32, height: u32) {
-        let mut b = false;
-        self.plot.keybinds(&self.input_state);
-        self.plot
-            .set_screen(width as f64, height as f64, true, true);
-        #[cfg(feature = "bincode")]
-        if let Some(tiny) = std::mem::take(&mut self.tiny) {
-            self.plot.apply_tiny(tiny);
-        }
-        if let Some(n) = self.data.update(&mut self.plot) {
-            b = true;
-            self.name = n;
-        };
+        let b = self.prepare(width, height);
         let mut v = Vec::new();
         #[cfg(not(feature = "tiny-skia"))]
         self.plot.update(width, height, &mut v);
@@ -308,40 +508,21 @@ impl App {
         let canvas = self.plot.canvas.as_ref().unwrap();
         rupl::draw(canvas.data(), canvas.width());
         if b {
-            let name = self.name.clone();
+            let title = self.window_title();
             if let Some(w) = self.window() {
-                if name.is_empty() {
-                    w.set_title("kalc-plot");
-                } else {
-                    w.set_title(&name);
-                }
+                w.set_title(&title);
             }
         }
     }
     #[cfg(feature = "wasm")]
     #[cfg(feature = "wasm-draw")]
     pub(crate) fn main(&mut self, width: u32, height: u32) {
-        let mut b = false;
-        self.plot.keybinds(&self.input_state);
-        self.plot
-            .set_screen(width as f64, height as f64, true, true);
-        #[cfg(feature = "bincode")]
-        if let Some(tiny) = std::mem::take(&mut self.tiny) {
-            self.plot.apply_tiny(tiny);
-        }
-        if let Some(n) = self.data.update(&mut self.plot) {
-            b = true;
-            self.name = n;
-        };
+        let b = self.prepare(width, height);
         self.plot.update(width, height);
         if b {
-            let name = self.name.clone();
+            let title = self.window_title();
             if let Some(w) = self.window() {
-                if name.is_empty() {
-                    w.set_title("kalc-plot");
-                } else {
-                    w.set_title(&name);
-                }
+                w.set_title(&title);
             }
         }
     }
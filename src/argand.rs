@@ -0,0 +1,97 @@
+use crate::data::{Data, Plot};
+use rupl::types::{Complex, GraphData};
+//arrowheads along a trajectory, evenly spaced in x so their spacing shows how fast f moves
+const ARROWS: usize = 16;
+//arrowhead length as a fraction of the trajectory's extent
+const HEAD: f64 = 1.0 / 48.0;
+//the arrowhead at b pointing away from a, as a wing, the tip and the other wing
+fn head(a: (f64, f64), b: (f64, f64), size: f64) -> Option<GraphData> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let d = dx.hypot(dy);
+    if !(d > 0.0 && d.is_finite() && b.0.is_finite() && b.1.is_finite()) {
+        return None;
+    }
+    let (ux, uy) = (dx / d * size, dy / d * size);
+    //the wings sit 150 degrees either side of the direction of travel
+    let (s, c) = (0.5, -(3f64.sqrt()) / 2.0);
+    let wing = |s: f64| (b.0 + ux * c - uy * s, Complex::Real(b.1 + ux * s + uy * c));
+    Some(GraphData::Coord(vec![
+        wing(s),
+        (b.0, Complex::Real(b.1)),
+        wing(-s),
+    ]))
+}
+//the samples of f over x as the points (re f, im f) of the plane
+fn trajectory(v: &[Complex]) -> Vec<(f64, f64)> {
+    v.iter()
+        .map(|c| match c {
+            Complex::Real(re) => (*re, 0.0),
+            Complex::Imag(im) => (0.0, *im),
+            Complex::Complex(re, im) => (*re, *im),
+        })
+        .collect()
+}
+impl Data {
+    //the path f traces through the complex plane as x runs from start to end, with arrowheads
+    //showing which way x increases
+    pub(crate) fn get_argand(
+        &self,
+        data: &Plot,
+        start: f64,
+        end: f64,
+        len: usize,
+        buffer: &mut GraphData,
+    ) -> Option<bool> {
+        let complex = self.get_2d(data, start, end, len, buffer)?;
+        let GraphData::Width(v, _, _) = buffer else {
+            return Some(complex);
+        };
+        let points = trajectory(v);
+        let finite = points
+            .iter()
+            .filter(|(x, y)| x.is_finite() && y.is_finite());
+        let (lo, hi) = finite.fold(
+            (
+                (f64::INFINITY, f64::INFINITY),
+                (f64::NEG_INFINITY, f64::NEG_INFINITY),
+            ),
+            |(lo, hi), (x, y)| ((lo.0.min(*x), lo.1.min(*y)), (hi.0.max(*x), hi.1.max(*y))),
+        );
+        let size = (hi.0 - lo.0).hypot(hi.1 - lo.1) * HEAD;
+        let heads = (1..=ARROWS).filter_map(|k| {
+            let i = (k * (points.len() - 1) / (ARROWS + 1)).max(1);
+            head(points[i - 1], points[i], size)
+        });
+        *buffer = GraphData::List(
+            std::iter::once(GraphData::Coord(
+                points
+                    .iter()
+                    .map(|(x, y)| (*x, Complex::Real(*y)))
+                    .collect(),
+            ))
+            .chain(heads)
+            .collect(),
+        );
+        Some(false)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn arrowheads() {
+        let Some(GraphData::Coord(head)) = head((0.0, 0.0), (1.0, 0.0), 1.0) else {
+            unreachable!()
+        };
+        let point = |(x, y): &(f64, Complex)| match y {
+            Complex::Real(y) => (*x, *y),
+            _ => unreachable!(),
+        };
+        let (a, tip, b) = (point(&head[0]), point(&head[1]), point(&head[2]));
+        assert_eq!(tip, (1.0, 0.0));
+        assert!(a.0 < 1.0 && b.0 < 1.0 && (a.1 + b.1).abs() < 1e-12);
+        assert!(((a.0 - 1.0).hypot(a.1) - 1.0).abs() < 1e-12);
+        assert!(head((1.0, 1.0), (1.0, 1.0), 1.0).is_none());
+        assert!(head((0.0, 0.0), (f64::NAN, 0.0), 1.0).is_none());
+    }
+}
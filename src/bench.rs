@@ -0,0 +1,277 @@
+extern crate test;
+use crate::data::{Data, domain, init};
+use kalc_lib::load_vars::get_vars;
+use kalc_lib::units::{GraphType, Options};
+use rupl::types::GraphData;
+#[cfg(feature = "bench-alloc")]
+use std::alloc::{GlobalAlloc, Layout, System};
+#[cfg(feature = "bench-alloc")]
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+use test::{Bencher, black_box};
+//counted per thread so tests allocating alongside a bench do not show up in its count, only with
+//bench-alloc so the rest of cargo test keeps the system allocator
+#[cfg(feature = "bench-alloc")]
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+#[cfg(feature = "bench-alloc")]
+fn allocations() -> usize {
+    ALLOCATIONS.try_with(Cell::get).unwrap_or(0)
+}
+#[cfg(feature = "bench-alloc")]
+fn count() {
+    let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+}
+#[cfg(feature = "bench-alloc")]
+struct Counting;
+#[cfg(feature = "bench-alloc")]
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        unsafe { System.alloc(layout) }
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+#[cfg(feature = "bench-alloc")]
+#[cfg(not(feature = "wee"))]
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    D2,
+    D3,
+    Domain,
+    Slice,
+    Init,
+    //a view too narrow for f64, sampled with rug
+    Deep,
+}
+//the options the app starts with, graph_prec 64 samples in f64 so only functions ending in
+//{precise} and deep views use rug
+fn data(function: &str, graphtype: Option<GraphType>) -> Data {
+    let mut options = Options {
+        prec: 128,
+        graph_prec: 64,
+        graphing: true,
+        ..Options::default()
+    };
+    if let Some(graphtype) = graphtype {
+        options.graphtype = graphtype
+    }
+    let vars = get_vars(options);
+    let (functions, _) = init(function, &mut options, vars.clone());
+    let mut data = Data {
+        data: Vec::new(),
+        errors: Vec::new(),
+        deep: None,
+        floor: false,
+        shown: Vec::new(),
+        sources: Vec::new(),
+        changes: None,
+        ruler: None,
+        domain: domain(options.graphtype),
+        options,
+        vars,
+        blacklist: Vec::new(),
+        var: rupl::types::Vec2::new(options.xr.0, options.xr.1),
+        count_changed: false,
+        refine: None,
+        budget: Default::default(),
+        #[cfg(feature = "bincode")]
+        session: None,
+    };
+    data.set_functions(functions);
+    data
+}
+fn interpreted(mut data: Data) -> Data {
+    for plot in data.data.iter_mut().flatten() {
+        plot.compiled = None
+    }
+    data
+}
+//how long evaluations per second are measured for before libtest times the bench
+const RATE: Duration = Duration::from_millis(100);
+//with bench-alloc runs f once on this thread alone to count its allocations, then prints the
+//evaluations per second on threads threads, both shown with -- --nocapture, before timing it
+fn measure<T: Send>(
+    b: &mut Bencher,
+    name: &str,
+    evals: usize,
+    threads: usize,
+    f: impl Fn() -> T + Sync,
+) {
+    #[cfg(feature = "bench-alloc")]
+    {
+        #[cfg(feature = "rayon")]
+        let here = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .use_current_thread()
+            .build()
+            .unwrap();
+        let before = allocations();
+        #[cfg(feature = "rayon")]
+        black_box(here.install(&f));
+        #[cfg(not(feature = "rayon"))]
+        black_box(f());
+        let allocations = allocations() - before;
+        println!(
+            "{name}: {allocations} allocations, {:.2} per evaluation",
+            allocations as f64 / evals.max(1) as f64
+        );
+    }
+    #[cfg(feature = "rayon")]
+    let pool = (threads != 0).then(|| {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap()
+    });
+    #[cfg(not(feature = "rayon"))]
+    assert_eq!(threads, 0);
+    let sample = || {
+        #[cfg(feature = "rayon")]
+        if let Some(pool) = &pool {
+            return pool.install(&f);
+        }
+        f()
+    };
+    let start = Instant::now();
+    let mut runs = 0;
+    while runs < 2 || start.elapsed() < RATE {
+        black_box(sample());
+        runs += 1
+    }
+    println!(
+        "{name}: {evals} evaluations, {:.3} million per second",
+        (evals * runs) as f64 / start.elapsed().as_secs_f64() / 1e6
+    );
+    b.iter(sample)
+}
+fn run(b: &mut Bencher, name: &str, threads: usize, function: &str, kind: Kind, len: usize) {
+    let data = data(
+        function,
+        (kind == Kind::Domain).then_some(GraphType::Domain),
+    );
+    let functions = data.data.len();
+    let evals = match kind {
+        Kind::D2 | Kind::Slice | Kind::Deep => (len + 1) * functions,
+        Kind::D3 | Kind::Domain => (len + 1) * (len + 1) * functions,
+        Kind::Init => function.split('#').count(),
+    };
+    let sample = || {
+        let mut graph = (0..functions)
+            .map(|_| GraphData::None)
+            .collect::<Vec<GraphData>>();
+        match kind {
+            Kind::D2 => {
+                data.generate_2d(-8.0, 8.0, len, None, &mut graph);
+            }
+            Kind::Deep => {
+                data.generate_2d(1.0, 1.0 + 1e-13, len, None, &mut graph);
+            }
+            Kind::D3 | Kind::Domain => {
+                data.generate_3d(-2.0, -2.0, 2.0, 2.0, len, len, None, &mut graph);
+            }
+            Kind::Slice => {
+                data.generate_2d_slice(-2.0, -2.0, 2.0, 2.0, len, len, 0, false, None, &mut graph);
+            }
+            Kind::Init => {
+                let mut options = data.options;
+                black_box(init(function, &mut options, data.vars.clone()));
+            }
+        }
+        graph
+    };
+    measure(b, name, evals, threads, sample)
+}
+macro_rules! corpus {
+    ($threads:expr, $($name:ident: $function:expr, $kind:ident, $len:expr;)*) => {
+        use super::*;
+        $(#[bench]
+        fn $name(b: &mut Bencher) {
+            run(
+                b,
+                concat!(module_path!(), "::", stringify!($name)),
+                $threads,
+                $function,
+                Kind::$kind,
+                $len,
+            )
+        })*
+    };
+}
+macro_rules! suite {
+    ($threads:expr) => {
+        corpus!(
+            $threads,
+            plot_2d_256: "sin(x)", D2, 256;
+            plot_2d_4096: "sin(x)", D2, 4096;
+            polynomial_2d_4096: "x^3-2x+1", D2, 4096;
+            complex_2d_4096: "sqrt(x)", D2, 4096;
+            special_2d_4096: "gamma(x)", D2, 4096;
+            list_2d_512: "±sqrt(x)", D2, 512;
+            parametric_2d_512: "{cos(x),sin(2x)}", D2, 512;
+            surface_32: "sin(x)cos(y)", D3, 32;
+            surface_128: "sin(x)cos(y)", D3, 128;
+            surface_complex_128: "sqrt(x+yi)", D3, 128;
+            domain_64: "sin(1/z)", Domain, 64;
+            domain_256: "sin(1/z)", Domain, 256;
+            slice_512: "sin(x)cos(y)", Slice, 512;
+            slice_4096: "sin(x)cos(y)", Slice, 4096;
+            init_single: "sin(x)", Init, 0;
+            init_many: "x^2#cos(x)#1/x#sqrt(x+yi)#{cos(x),sin(x),x/4}", Init, 0;
+            init_prelude: "a=2;b=3;a*sin(bx)#a*cos(bx)", Init, 0;
+            precise_2d_1024: "sin(x){precise}", D2, 1024;
+            precise_surface_32: "sin(x)cos(y){precise}", D3, 32;
+            deep_2d_1024: "sin(x)", Deep, 1024;
+        );
+    };
+}
+//the default rayon thread pool, or everything on the calling thread without rayon
+mod threads {
+    suite!(0);
+}
+//rayon forced onto one thread to separate parallel speedup from evaluation cost
+#[cfg(feature = "rayon")]
+mod single {
+    suite!(1);
+}
+fn grid(b: &mut Bencher, data: &Data, len: usize) {
+    assert!(data.data.iter().flatten().count() > 0);
+    b.iter(|| {
+        let mut graph = Vec::new();
+        data.generate_3d(-2.0, -2.0, 2.0, 2.0, len, len, None, &mut graph);
+        graph
+    })
+}
+#[bench]
+fn surface_compiled(b: &mut Bencher) {
+    let data = data("sin(x)cos(y)", None);
+    assert!(data.data[0].as_ref().unwrap().compiled.is_some());
+    grid(b, &data, 64)
+}
+#[bench]
+fn surface_interpreted(b: &mut Bencher) {
+    grid(b, &interpreted(data("sin(x)cos(y)", None)), 64)
+}
+#[bench]
+fn domain_compiled(b: &mut Bencher) {
+    let data = data("sin(1/z)", Some(GraphType::Domain));
+    assert!(data.data[0].as_ref().unwrap().compiled.is_some());
+    grid(b, &data, 256)
+}
+#[bench]
+fn domain_interpreted(b: &mut Bencher) {
+    grid(
+        b,
+        &interpreted(data("sin(1/z)", Some(GraphType::Domain))),
+        256,
+    )
+}
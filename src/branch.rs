@@ -0,0 +1,166 @@
+#[cfg(not(feature = "rayon"))]
+use crate::IntoIter;
+use crate::data::{Coord, Data, Plot, compact};
+use crate::sequence::close;
+use kalc_lib::units::Options;
+#[cfg(feature = "rayon")]
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use rupl::types::{Complex, GraphData};
+//how far under the negative real axis, as a fraction of a row, the lower half plane ends, so its
+//edge takes the values from below the cut
+const BELOW: f64 = 1e-9;
+//where the base of a power ending s starts, a bracket with the name of any function applied to
+//it or a run of letters and digits, taking in more than the base only across products
+fn base(s: &str) -> usize {
+    if s.ends_with(')') {
+        let mut depth = 0;
+        for (i, c) in s.char_indices().rev() {
+            match c {
+                ')' | '}' | ']' => depth += 1,
+                '(' | '{' | '[' => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                return s[..i].trim_end_matches(char::is_alphanumeric).len();
+            }
+        }
+        0
+    } else {
+        s.trim_end_matches(|c: char| c.is_alphanumeric() || c == '.')
+            .len()
+    }
+}
+//the text of sheet k of f, with every square root, logarithm and non integer power moved onto
+//its k'th branch, nested ones onto the same k
+pub(crate) fn sheet(s: &str, k: usize) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(c) = rest.chars().next() {
+        if c.is_alphabetic() && !out.ends_with(char::is_alphabetic) {
+            let end = rest
+                .find(|c: char| !c.is_alphanumeric())
+                .unwrap_or(rest.len());
+            let name = &rest[..end];
+            if matches!(name, "sqrt" | "ln" | "log")
+                && rest[end..].starts_with('(')
+                && let Some(i) = close(&rest[end + 1..])
+            {
+                let inner = &rest[end + 1..end + 1 + i];
+                let call = format!("{name}({})", sheet(inner, k));
+                out.push_str(&match name {
+                    "sqrt" => format!("((-1)^{k}*{call})"),
+                    //log(b,x) is left on its principal branch
+                    _ if inner.contains(',') => call,
+                    _ => format!("(2pi i*{k}+{call})"),
+                });
+                rest = &rest[end + 2 + i..];
+            } else {
+                out.push_str(name);
+                rest = &rest[end..];
+            }
+        } else if c == '^' {
+            let after = &rest[1..];
+            let len = if after.starts_with('(') {
+                close(&after[1..]).map_or(after.len(), |i| i + 2)
+            } else {
+                let sign = usize::from(after.starts_with('-'));
+                after[sign..]
+                    .find(|c: char| !(c.is_alphanumeric() || c == '.'))
+                    .map_or(after.len(), |i| i + sign)
+            };
+            let exponent = sheet(&after[..len], k);
+            let start = base(&out);
+            let power = format!("{}^{exponent}", &out[start..]);
+            let trimmed = exponent.trim_matches(|c| c == '(' || c == ')');
+            //e^a is exp(a) rather than a power with branches, even after a coefficient
+            let exp = out[start..].trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+            if exp == "e" || trimmed.parse::<i64>().is_ok() {
+                out.push('^');
+                out.push_str(&exponent)
+            } else {
+                out.truncate(start);
+                out.push_str(&format!("({power}*e^(2pi i*{k}*{exponent}))"))
+            }
+            rest = &after[len..];
+        } else {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    out
+}
+impl Data {
+    //every sheet of f over the grid, the principal one first, so a multivalued function is drawn
+    //whole instead of cut along its branch cuts, square roots, logarithms and powers of z cross
+    //onto the next sheet along the negative real axis so where the view spans it each sheet is
+    //sampled as its lower and upper half planes, and the upper half of sheet k meets the lower
+    //half of sheet k+1 along the axis instead of each sheet falling off a cliff there
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn get_branches(
+        &self,
+        data: &Plot,
+        startx: f64,
+        starty: f64,
+        endx: f64,
+        endy: f64,
+        lenx: usize,
+        leny: usize,
+        options: Options,
+        buffer: &mut GraphData,
+    ) -> Option<bool> {
+        let (dx, span) = ((endx - startx) / lenx as f64, (endy - starty) / leny as f64);
+        let halves = if starty < 0.0 && endy > 0.0 && leny >= 2 {
+            let below = ((-starty / span).round() as usize).clamp(1, leny - 1);
+            vec![(starty, -BELOW * span, below), (0.0, endy, leny - below)]
+        } else {
+            vec![(starty, endy, leny)]
+        };
+        let mut complex = false;
+        let sheets = (0..=data.branches.len())
+            .flat_map(|k| halves.iter().map(move |half| (k, *half)))
+            .map(|(k, (starty, endy, leny))| {
+                let dy = (endy - starty) / leny as f64;
+                let v = (0..=leny)
+                    .into_par_iter()
+                    .flat_map(|j| {
+                        let y = Coord::new(starty, j as f64 * dy);
+                        let modified = match k {
+                            0 => data.bind("y", y, options),
+                            _ => data.branches[k - 1].bind("y", y, options),
+                        };
+                        (0..=lenx)
+                            .map(|i| {
+                                modified
+                                    .eval("x", Coord::new(startx, i as f64 * dx), options)
+                                    .complex()
+                            })
+                            .collect::<Vec<Complex>>()
+                    })
+                    .collect::<Vec<Complex>>();
+                let (v, b) = compact(v);
+                complex |= b;
+                GraphData::Width3D(v, startx, starty, endx, endy)
+            })
+            .collect();
+        *buffer = GraphData::List(sheets);
+        Some(complex)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn sheets() {
+        assert_eq!(sheet("sqrt(z)", 1), "((-1)^1*sqrt(z))");
+        assert_eq!(sheet("ln(z)+log(2,z)", 2), "(2pi i*2+ln(z))+log(2,z)");
+        assert_eq!(sheet("1/z^(1/3)", 1), "1/(z^(1/3)*e^(2pi i*1*(1/3)))");
+        assert_eq!(
+            sheet("sin(z)^0.5+2e^z+z^2", 1),
+            "(sin(z)^0.5*e^(2pi i*1*0.5))+2e^z+z^2"
+        );
+        assert_eq!(
+            sheet("sqrt(1+sqrt(z))", 1),
+            "((-1)^1*sqrt(1+((-1)^1*sqrt(z))))"
+        );
+    }
+}
@@ -0,0 +1,540 @@
+use crate::eval::Value;
+use crate::{C, F, I};
+use kalc_lib::complex::NumStr;
+use kalc_lib::types::{Complex as Comp, Float, FloatShared};
+use kalc_lib::units::{AngleType, Options};
+use std::sync::Arc;
+//variables a compiled function can be sampled over
+const VARS: [&str; 2] = ["x", "y"];
+//deepest stack a compiled function may use, anything deeper falls back to kalc-lib
+const STACK: usize = 32;
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Cx {
+    re: f64,
+    im: f64,
+}
+impl Cx {
+    const NAN: Cx = Cx::new(f64::NAN, f64::NAN);
+    const fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+    fn real(re: f64) -> Self {
+        Self::new(re, 0.0)
+    }
+    fn is_real(self) -> bool {
+        self.im == 0.0
+    }
+    fn add(self, b: Cx) -> Cx {
+        Cx::new(self.re + b.re, self.im + b.im)
+    }
+    fn sub(self, b: Cx) -> Cx {
+        Cx::new(self.re - b.re, self.im - b.im)
+    }
+    fn mul(self, b: Cx) -> Cx {
+        if self.is_real() && b.is_real() {
+            return Cx::real(self.re * b.re);
+        }
+        Cx::new(
+            self.re * b.re - self.im * b.im,
+            self.re * b.im + self.im * b.re,
+        )
+    }
+    fn div(self, b: Cx) -> Cx {
+        if self.is_real() && b.is_real() {
+            return Cx::real(self.re / b.re);
+        }
+        if b.is_real() {
+            return Cx::new(self.re / b.re, self.im / b.re);
+        }
+        let d = b.re * b.re + b.im * b.im;
+        Cx::new(
+            (self.re * b.re + self.im * b.im) / d,
+            (self.im * b.re - self.re * b.im) / d,
+        )
+    }
+    fn neg(self) -> Cx {
+        Cx::new(-self.re, -self.im)
+    }
+    fn norm(self) -> f64 {
+        self.re.hypot(self.im)
+    }
+    fn abs(self) -> Cx {
+        Cx::real(self.norm())
+    }
+    fn exp(self) -> Cx {
+        let r = self.re.exp();
+        if self.is_real() {
+            return Cx::real(r);
+        }
+        Cx::new(r * self.im.cos(), r * self.im.sin())
+    }
+    fn ln(self) -> Cx {
+        if self.is_real() && self.re >= 0.0 {
+            return Cx::real(self.re.ln());
+        }
+        Cx::new(self.norm().ln(), self.im.atan2(self.re))
+    }
+    fn sqrt(self) -> Cx {
+        if self.is_real() {
+            return if self.re >= 0.0 {
+                Cx::real(self.re.sqrt())
+            } else {
+                Cx::new(0.0, (-self.re).sqrt())
+            };
+        }
+        let r = self.norm();
+        let re = ((r + self.re) / 2.0).sqrt();
+        let im = ((r - self.re) / 2.0).sqrt();
+        Cx::new(re, im.copysign(self.im))
+    }
+    fn sin(self) -> Cx {
+        if self.is_real() {
+            return Cx::real(self.re.sin());
+        }
+        Cx::new(
+            self.re.sin() * self.im.cosh(),
+            self.re.cos() * self.im.sinh(),
+        )
+    }
+    fn cos(self) -> Cx {
+        if self.is_real() {
+            return Cx::real(self.re.cos());
+        }
+        Cx::new(
+            self.re.cos() * self.im.cosh(),
+            -self.re.sin() * self.im.sinh(),
+        )
+    }
+    fn tan(self) -> Cx {
+        if self.is_real() {
+            return Cx::real(self.re.tan());
+        }
+        self.sin().div(self.cos())
+    }
+    fn sinh(self) -> Cx {
+        if self.is_real() {
+            return Cx::real(self.re.sinh());
+        }
+        Cx::new(
+            self.re.sinh() * self.im.cos(),
+            self.re.cosh() * self.im.sin(),
+        )
+    }
+    fn cosh(self) -> Cx {
+        if self.is_real() {
+            return Cx::real(self.re.cosh());
+        }
+        Cx::new(
+            self.re.cosh() * self.im.cos(),
+            self.re.sinh() * self.im.sin(),
+        )
+    }
+    fn tanh(self) -> Cx {
+        if self.is_real() {
+            return Cx::real(self.re.tanh());
+        }
+        self.sinh().div(self.cosh())
+    }
+    fn pow(self, b: Cx) -> Cx {
+        if self.is_real() && b.is_real() && (self.re >= 0.0 || b.re.fract() == 0.0) {
+            return Cx::real(self.re.powf(b.re));
+        }
+        if self.re == 0.0 && self.im == 0.0 {
+            return if b.re > 0.0 { Cx::real(0.0) } else { Cx::NAN };
+        }
+        b.mul(self.ln()).exp()
+    }
+    fn powi(self, n: i32) -> Cx {
+        let mut base = if n < 0 { Cx::real(1.0).div(self) } else { self };
+        let mut n = n.unsigned_abs();
+        let mut acc = Cx::real(1.0);
+        while n > 0 {
+            if n & 1 == 1 {
+                acc = acc.mul(base)
+            }
+            base = base.mul(base);
+            n >>= 1;
+        }
+        acc
+    }
+}
+fn function(name: &str) -> Option<fn(Cx) -> Cx> {
+    Some(match name {
+        "sin" => Cx::sin,
+        "cos" => Cx::cos,
+        "tan" => Cx::tan,
+        "sinh" => Cx::sinh,
+        "cosh" => Cx::cosh,
+        "tanh" => Cx::tanh,
+        "exp" => Cx::exp,
+        "ln" => Cx::ln,
+        "sqrt" => Cx::sqrt,
+        "abs" => Cx::abs,
+        _ => return None,
+    })
+}
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Const(Cx),
+    Var(usize),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Neg,
+    Pow,
+    PowI(i32),
+    Call(fn(Cx) -> Cx),
+}
+//a function compiled into postfix ops over f64 complex numbers, with the sampled variables bound so far
+#[derive(Clone, Debug)]
+pub(crate) struct Compiled {
+    ops: Arc<[Op]>,
+    vars: [Cx; VARS.len()],
+}
+struct Compiler<'a> {
+    tokens: &'a [NumStr<I, F, C>],
+    //the compiled trig functions take radians, other angle modes are left to kalc-lib
+    radians: bool,
+    pos: usize,
+    ops: Vec<Op>,
+    depth: usize,
+    max: usize,
+}
+impl Compiler<'_> {
+    fn peek(&self) -> Option<&NumStr<I, F, C>> {
+        self.tokens.get(self.pos)
+    }
+    fn eat(&mut self, f: impl Fn(&NumStr<I, F, C>) -> bool) -> bool {
+        if self.peek().is_some_and(f) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+    fn push(&mut self, op: Op) {
+        match op {
+            Op::Const(_) | Op::Var(_) => {
+                self.depth += 1;
+                self.max = self.max.max(self.depth)
+            }
+            Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Pow => self.depth -= 1,
+            Op::Neg | Op::PowI(_) | Op::Call(_) => {}
+        }
+        self.ops.push(op)
+    }
+    fn sum(&mut self) -> Option<()> {
+        self.product()?;
+        loop {
+            if self.eat(|t| matches!(t, NumStr::Plus)) {
+                self.product()?;
+                self.push(Op::Add)
+            } else if self.eat(|t| matches!(t, NumStr::Minus)) {
+                self.product()?;
+                self.push(Op::Sub)
+            } else {
+                return Some(());
+            }
+        }
+    }
+    fn product(&mut self) -> Option<()> {
+        self.implied()?;
+        loop {
+            if self.eat(|t| matches!(t, NumStr::Multiplication)) {
+                self.implied()?;
+                self.push(Op::Mul)
+            } else if self.eat(|t| matches!(t, NumStr::Division)) {
+                self.implied()?;
+                self.push(Op::Div)
+            } else {
+                return Some(());
+            }
+        }
+    }
+    fn implied(&mut self) -> Option<()> {
+        self.unary()?;
+        while self.eat(|t| matches!(t, NumStr::InternalMultiplication)) {
+            self.unary()?;
+            self.push(Op::Mul)
+        }
+        Some(())
+    }
+    fn unary(&mut self) -> Option<()> {
+        if self.eat(|t| matches!(t, NumStr::Minus)) {
+            self.unary()?;
+            self.push(Op::Neg);
+            Some(())
+        } else {
+            self.power()
+        }
+    }
+    fn power(&mut self) -> Option<()> {
+        self.primary()?;
+        if self.eat(|t| matches!(t, NumStr::Exponent)) {
+            self.unary()?;
+            if let Some(Op::Const(n)) = self.ops.last()
+                && n.is_real()
+                && n.re.fract() == 0.0
+                && n.re.abs() <= 64.0
+            {
+                let n = n.re as i32;
+                self.ops.pop();
+                self.depth -= 1;
+                self.push(Op::PowI(n))
+            } else {
+                self.push(Op::Pow)
+            }
+        }
+        Some(())
+    }
+    fn primary(&mut self) -> Option<()> {
+        let token = self.peek()?;
+        self.pos += 1;
+        match token {
+            NumStr::Num(n) => self.push(Op::Const(Cx::new(
+                n.number.real().to_f64(),
+                n.number.imag().to_f64(),
+            ))),
+            NumStr::Func(name) => {
+                if let Some(i) = VARS.iter().position(|v| v == name) {
+                    self.push(Op::Var(i))
+                } else {
+                    if !self.radians && matches!(name.as_str(), "sin" | "cos" | "tan") {
+                        return None;
+                    }
+                    let f = function(name)?;
+                    if !self.eat(|t| matches!(t, NumStr::LeftBracket)) {
+                        return None;
+                    }
+                    self.sum()?;
+                    if !self.eat(|t| matches!(t, NumStr::RightBracket)) {
+                        return None;
+                    }
+                    self.push(Op::Call(f))
+                }
+            }
+            NumStr::LeftBracket => {
+                self.sum()?;
+                if !self.eat(|t| matches!(t, NumStr::RightBracket)) {
+                    return None;
+                }
+            }
+            _ => return None,
+        }
+        Some(())
+    }
+}
+//compiles a parsed function, giving up on anything beyond plain complex arithmetic so kalc-lib handles it
+#[allow(clippy::type_complexity)]
+pub(crate) fn compile(
+    func: &[NumStr<I, F, C>],
+    funcvar: &[(String, Vec<NumStr<I, F, C>>)],
+    options: &Options,
+) -> Option<Compiled> {
+    if !funcvar.is_empty() {
+        return None;
+    }
+    let mut compiler = Compiler {
+        tokens: func,
+        radians: matches!(options.angle, AngleType::Radians),
+        pos: 0,
+        ops: Vec::new(),
+        depth: 0,
+        max: 0,
+    };
+    compiler.sum()?;
+    if compiler.pos != func.len() || compiler.depth != 1 || compiler.max > STACK {
+        return None;
+    }
+    Some(Compiled {
+        ops: compiler.ops.into(),
+        vars: [Cx::NAN; VARS.len()],
+    })
+}
+impl Compiled {
+    pub(crate) fn bind(&self, var: &str, v: f64) -> Self {
+        let mut compiled = self.clone();
+        if let Some(i) = VARS.iter().position(|n| *n == var) {
+            compiled.vars[i] = Cx::real(v)
+        }
+        compiled
+    }
+    pub(crate) fn eval(&self, var: &str, v: f64) -> Value {
+        let mut vars = self.vars;
+        if let Some(i) = VARS.iter().position(|n| *n == var) {
+            vars[i] = Cx::real(v)
+        }
+        let v = self.run(vars);
+        Value::Num(v.re, v.im)
+    }
+    //f with x and y set to the complex numbers z and c, for iterating f as a map of z
+    pub(crate) fn step(&self, z: (f64, f64), c: (f64, f64)) -> (f64, f64) {
+        let v = self.run([Cx::new(z.0, z.1), Cx::new(c.0, c.1)]);
+        (v.re, v.im)
+    }
+    fn run(&self, vars: [Cx; VARS.len()]) -> Cx {
+        let mut stack = [Cx::NAN; STACK];
+        let mut len = 0;
+        for op in self.ops.iter() {
+            match *op {
+                Op::Const(c) => {
+                    stack[len] = c;
+                    len += 1
+                }
+                Op::Var(i) => {
+                    stack[len] = vars[i];
+                    len += 1
+                }
+                Op::Neg => stack[len - 1] = stack[len - 1].neg(),
+                Op::PowI(n) => stack[len - 1] = stack[len - 1].powi(n),
+                Op::Call(f) => stack[len - 1] = f(stack[len - 1]),
+                Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Pow => {
+                    len -= 1;
+                    let (a, b) = (stack[len - 1], stack[len]);
+                    stack[len - 1] = match op {
+                        Op::Add => a.add(b),
+                        Op::Sub => a.sub(b),
+                        Op::Mul => a.mul(b),
+                        Op::Div => a.div(b),
+                        _ => a.pow(b),
+                    }
+                }
+            }
+        }
+        stack[0]
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Coord, init};
+    use crate::eval::Tokens;
+    use kalc_lib::load_vars::get_vars;
+    fn options() -> Options {
+        Options {
+            prec: 64,
+            graph_prec: 64,
+            graphing: true,
+            ..Options::default()
+        }
+    }
+    fn tokens(function: &str) -> Tokens {
+        let mut options = options();
+        let vars = get_vars(options);
+        let (functions, _) = init(function, &mut options, vars);
+        let f = functions.into_iter().next().unwrap();
+        Tokens::F64(f.func, f.funcvar)
+    }
+    fn compiled(function: &str) -> Option<Compiled> {
+        let Tokens::F64(func, funcvar) = tokens(function) else {
+            unreachable!()
+        };
+        compile(&func, &funcvar, &options())
+    }
+    fn close(a: &Value, b: &Value) -> bool {
+        let (Value::Num(ar, ai), Value::Num(br, bi)) = (a, b) else {
+            return false;
+        };
+        let (fa, fb) = (
+            ar.is_finite() && ai.is_finite(),
+            br.is_finite() && bi.is_finite(),
+        );
+        if !fa || !fb {
+            return fa == fb;
+        }
+        let eq = |a: f64, b: f64| (a - b).abs() <= 1e-9 * a.abs().max(b.abs()).max(1.0);
+        eq(*ar, *br) && eq(*ai, *bi)
+    }
+    fn check(function: &str) {
+        let interpreted = tokens(function);
+        let compiled = compiled(function).unwrap_or_else(|| panic!("{function} did not compile"));
+        for y in [-2.5, -0.5, 0.0, 0.75, 3.0] {
+            let a = interpreted.bind("y", Coord::new(y, 0.0), options());
+            let b = compiled.bind("y", y);
+            for x in [-3.0, -1.0, -0.25, 0.0, 0.5, 2.0, 7.5] {
+                let a = a.eval("x", Coord::new(x, 0.0), options());
+                let b = b.eval("x", x);
+                assert!(close(&a, &b), "{function} at ({x},{y})");
+            }
+        }
+    }
+    #[test]
+    fn matches_kalc() {
+        for function in [
+            "x",
+            "-x^2+3x-1",
+            "1/x",
+            "2^x",
+            "x^-2",
+            "x^0.5",
+            "sqrt(x)",
+            "ln(x)",
+            "exp(-x^2)",
+            "sin(x)cos(y)",
+            "tan(x)/(1+y^2)",
+            "sinh(x)-cosh(y)*tanh(x)",
+            "abs(x-y)",
+            "(x+yi)^2",
+            "sin(1/(x+yi))",
+            "((x+yi)^2-1)/((x+yi)^2+1)",
+            "sqrt(x+yi)",
+            "(x+yi)^(1/3)",
+        ] {
+            check(function)
+        }
+    }
+    #[test]
+    fn real_stays_real() {
+        let Value::Num(_, im) = compiled("sin(x)^2+x^3").unwrap().eval("x", 1.3) else {
+            unreachable!()
+        };
+        assert_eq!(im, 0.0);
+    }
+    #[test]
+    fn degrees_match_kalc() {
+        let mut options = Options {
+            angle: AngleType::Degrees,
+            ..options()
+        };
+        let vars = get_vars(options);
+        let (functions, _) = init("sin(x)cos(y)#x^2+sinh(x)", &mut options, vars);
+        //trig is left to kalc-lib, the rest still compiles
+        for (f, compiles) in functions.into_iter().zip([false, true]) {
+            let interpreted = Tokens::F64(f.func.clone(), f.funcvar.clone());
+            let plot = f.into_parts().0.unwrap();
+            assert_eq!(plot.compiled.is_some(), compiles);
+            for y in [0.0, 60.0] {
+                let a = plot.bind("y", Coord::new(y, 0.0), options);
+                let b = interpreted.bind("y", Coord::new(y, 0.0), options);
+                for x in [-30.0, 0.0, 45.0, 90.0] {
+                    let a = a.eval("x", Coord::new(x, 0.0), options);
+                    let b = b.eval("x", Coord::new(x, 0.0), options);
+                    assert!(close(&a, &b), "{x},{y}");
+                }
+            }
+        }
+        let mut options = Options {
+            angle: AngleType::Degrees,
+            ..options()
+        };
+        let vars = get_vars(options);
+        let (functions, _) = init("sin(x)", &mut options, vars);
+        let plot = functions
+            .into_iter()
+            .next()
+            .unwrap()
+            .into_parts()
+            .0
+            .unwrap();
+        let Value::Num(re, _) = plot.eval("x", Coord::new(90.0, 0.0), options) else {
+            unreachable!()
+        };
+        assert!((re - 1.0).abs() < 1e-9);
+    }
+    #[test]
+    fn falls_back() {
+        assert!(compiled("±x").is_none());
+        assert!(compiled("{x,2x}").is_none());
+    }
+}
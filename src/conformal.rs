@@ -0,0 +1,77 @@
+#[cfg(not(feature = "rayon"))]
+use crate::IntoIter;
+use crate::data::{Conformal, Coord, Data, Plot, Sampling};
+use crate::eval::Value;
+#[cfg(feature = "rayon")]
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use rupl::types::{Complex, GraphData};
+use std::f64::consts::TAU;
+impl Data {
+    //f at x+yi
+    pub(crate) fn at(&self, data: &Plot, x: f64, y: f64) -> (f64, f64) {
+        let options = self.sample_options(0);
+        match data
+            .bind("x", Coord::new(x, 0.0), options)
+            .eval("y", Coord::new(y, 0.0), options)
+        {
+            Value::Num(re, im) => (re, im),
+            _ => (f64::NAN, f64::NAN),
+        }
+    }
+    //f at x+yi as a point of the plane
+    pub(crate) fn image(&self, data: &Plot, x: f64, y: f64) -> (f64, Complex) {
+        match self.at(data, x, y) {
+            (re, im) if re.is_finite() && im.is_finite() => (re, Complex::Real(im)),
+            _ => (f64::NAN, Complex::Real(f64::NAN)),
+        }
+    }
+    //the images of the grid lines x=a and y=b over start..end on both axes, y limited by the
+    //function's own y range, or of the circles and rays about 0 reaching the furthest edge when polar
+    pub(crate) fn get_conformal(
+        &self,
+        data: &Plot,
+        conformal: &Conformal,
+        start: f64,
+        end: f64,
+        len: usize,
+        buffer: &mut GraphData,
+    ) -> Option<bool> {
+        let n = conformal.lines;
+        let (starty, endy, _) = Sampling::range(data.sampling.y, start, end, len)?;
+        let lines = if conformal.polar { 2 * n } else { 2 * (n + 1) };
+        let curves = (0..lines)
+            .into_par_iter()
+            .map(|i| {
+                let points = (0..=len).map(|j| j as f64 / len as f64);
+                GraphData::Coord(if conformal.polar {
+                    let radius = start.abs().max(end.abs());
+                    if i < n {
+                        let r = radius * (i + 1) as f64 / n as f64;
+                        points
+                            .map(|t| self.image(data, r * (TAU * t).cos(), r * (TAU * t).sin()))
+                            .collect()
+                    } else {
+                        let theta = TAU * (i - n) as f64 / n as f64;
+                        points
+                            .map(|t| {
+                                self.image(data, radius * t * theta.cos(), radius * t * theta.sin())
+                            })
+                            .collect()
+                    }
+                } else if i <= n {
+                    let x = start + (end - start) * i as f64 / n as f64;
+                    points
+                        .map(|t| self.image(data, x, starty + (endy - starty) * t))
+                        .collect()
+                } else {
+                    let y = starty + (endy - starty) * (i - n - 1) as f64 / n as f64;
+                    points
+                        .map(|t| self.image(data, start + (end - start) * t, y))
+                        .collect()
+                })
+            })
+            .collect::<Vec<GraphData>>();
+        *buffer = GraphData::List(curves);
+        Some(false)
+    }
+}
@@ -0,0 +1,445 @@
+use crate::App;
+use crate::data::Limit;
+use rupl::types::{Complex, Graph, GraphData, GraphMode};
+use std::io::{BufRead, BufReader, Write};
+#[cfg(unix)]
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::sync::mpsc::{Receiver, Sender, TryRecvError, channel};
+pub(crate) struct Request {
+    command: String,
+    reply: Sender<String>,
+}
+pub(crate) struct Control {
+    recv: Receiver<Request>,
+    //the socket file this process bound and its device and inode, so only that file is removed
+    #[cfg(unix)]
+    path: std::path::PathBuf,
+    #[cfg(unix)]
+    id: (u64, u64),
+}
+#[cfg(unix)]
+fn socket_id(path: &std::path::Path) -> Option<(u64, u64)> {
+    let meta = std::fs::symlink_metadata(path).ok()?;
+    meta.file_type()
+        .is_socket()
+        .then(|| (meta.dev(), meta.ino()))
+}
+#[cfg(unix)]
+impl Drop for Control {
+    fn drop(&mut self) {
+        if socket_id(&self.path) == Some(self.id) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+#[cfg(unix)]
+pub(crate) fn default_addr() -> Option<String> {
+    std::env::temp_dir()
+        .join(format!("kalc-plot-{}.sock", std::process::id()))
+        .to_str()
+        .map(|s| s.to_owned())
+}
+#[cfg(not(unix))]
+pub(crate) fn default_addr() -> Option<String> {
+    Some("127.0.0.1:0".to_owned())
+}
+pub(crate) fn spawn(
+    addr: String,
+    wake: impl Fn() + Send + Clone + 'static,
+) -> std::io::Result<Control> {
+    let (send, recv) = channel();
+    #[cfg(unix)]
+    let (listener, id) = {
+        //a stale socket from an earlier run is replaced, anything else at the path is left alone
+        match std::fs::symlink_metadata(&addr) {
+            Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(&addr)?,
+            Ok(_) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    format!("{addr} exists and is not a socket"),
+                ));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        let listener = std::os::unix::net::UnixListener::bind(&addr)?;
+        let id = socket_id(addr.as_ref()).ok_or_else(|| {
+            std::io::Error::other(format!("{addr} is not a socket after binding"))
+        })?;
+        (listener, id)
+    };
+    #[cfg(not(unix))]
+    let listener = std::net::TcpListener::bind(&addr)?;
+    #[cfg(unix)]
+    eprintln!("control socket at {addr}");
+    #[cfg(not(unix))]
+    eprintln!("control socket at {}", listener.local_addr()?);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let send = send.clone();
+            let wake = wake.clone();
+            std::thread::spawn(move || {
+                let Ok(mut writer) = stream.try_clone() else {
+                    return;
+                };
+                for line in BufReader::new(stream).lines() {
+                    let Ok(command) = line else {
+                        return;
+                    };
+                    if command.trim().is_empty() {
+                        continue;
+                    }
+                    let (reply, recv) = channel();
+                    if send.send(Request { command, reply }).is_err() {
+                        return;
+                    }
+                    wake();
+                    let Ok(reply) = recv.recv() else {
+                        return;
+                    };
+                    if writeln!(writer, "{reply}").is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+    Ok(Control {
+        recv,
+        #[cfg(unix)]
+        path: addr.into(),
+        #[cfg(unix)]
+        id,
+    })
+}
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+fn num(f: f64) -> String {
+    if f.is_finite() {
+        f.to_string()
+    } else {
+        "null".to_owned()
+    }
+}
+fn ok() -> String {
+    "{\"ok\":true}".to_owned()
+}
+fn err(e: impl std::fmt::Display) -> String {
+    format!("{{\"ok\":false,\"error\":{}}}", escape(&e.to_string()))
+}
+fn float(arg: Option<&str>) -> Result<f64, String> {
+    let arg = arg.ok_or("missing number")?;
+    arg.parse().map_err(|_| format!("not a number: {arg}"))
+}
+fn toggle(arg: Option<&str>, current: bool) -> Result<bool, String> {
+    match arg {
+        None => Ok(!current),
+        Some("on") => Ok(true),
+        Some("off") => Ok(false),
+        Some(arg) => Err(format!("expected on or off, got {arg}")),
+    }
+}
+fn parts(c: &Complex) -> (f64, f64) {
+    let (re, im) = c.to_options();
+    (re.unwrap_or(0.0), im.unwrap_or(0.0))
+}
+//grids holds the samples along each axis of each function's 3d grid
+fn csv(graph: &[GraphData], grids: &[Option<(usize, usize)>]) -> String {
+    fn rows(out: &mut String, i: usize, data: &GraphData, grid: Option<(usize, usize)>) {
+        match data {
+            GraphData::Width(v, s, e) => {
+                let dx = (e - s) / v.len().saturating_sub(1).max(1) as f64;
+                for (j, c) in v.iter().enumerate() {
+                    let (re, im) = parts(c);
+                    out.push_str(&format!("{i},{},,{re},{im}\n", s + j as f64 * dx))
+                }
+            }
+            GraphData::Coord(v) => {
+                for (x, c) in v {
+                    let (re, im) = parts(c);
+                    out.push_str(&format!("{i},{x},,{re},{im}\n"))
+                }
+            }
+            GraphData::Width3D(v, sx, sy, ex, ey) => {
+                let Some((lenx, leny)) = grid.filter(|(x, y)| (x + 1) * (y + 1) == v.len()) else {
+                    for c in v {
+                        let (re, im) = parts(c);
+                        out.push_str(&format!("{i},,,{re},{im}\n"))
+                    }
+                    return;
+                };
+                let (dx, dy) = ((ex - sx) / lenx as f64, (ey - sy) / leny as f64);
+                for (j, c) in v.iter().enumerate() {
+                    let (re, im) = parts(c);
+                    let (x, y) = (
+                        sx + (j % (lenx + 1)) as f64 * dx,
+                        sy + (j / (lenx + 1)) as f64 * dy,
+                    );
+                    out.push_str(&format!("{i},{x},{y},{re},{im}\n"))
+                }
+            }
+            GraphData::Coord3D(v) => {
+                for (x, y, c) in v {
+                    let (re, im) = parts(c);
+                    out.push_str(&format!("{i},{x},{y},{re},{im}\n"))
+                }
+            }
+            GraphData::Constant(c, _) => {
+                let (re, im) = parts(c);
+                out.push_str(&format!("{i},,,{re},{im}\n"))
+            }
+            GraphData::Point(p) => out.push_str(&format!("{i},{},,{},0\n", p.x, p.y)),
+            GraphData::List(l) => {
+                for data in l {
+                    rows(out, i, data, grid)
+                }
+            }
+            GraphData::None => {}
+        }
+    }
+    let mut out = "function,x,y,re,im\n".to_owned();
+    for (i, data) in graph.iter().enumerate() {
+        rows(&mut out, i, data, grids.get(i).copied().flatten())
+    }
+    out
+}
+impl App {
+    pub(crate) fn poll_control(&mut self) -> (Option<String>, bool) {
+        let Some(mut control) = self.control.take() else {
+            return (None, false);
+        };
+        let mut name = None;
+        let mut close = false;
+        let mut keep = true;
+        loop {
+            match control.recv.try_recv() {
+                Ok(Request { command, reply }) => {
+                    let (command, arg) = command
+                        .trim()
+                        .split_once(' ')
+                        .map(|(a, b)| (a, b.trim()))
+                        .unwrap_or((command.trim(), ""));
+                    let mut args = arg.split_whitespace();
+                    let ret = match command {
+                        "function" => {
+                            self.data.set_function(&mut self.plot, arg);
+                            name = Some(arg.to_owned());
+                            ok()
+                        }
+                        "view" => match (float(args.next()), float(args.next())) {
+                            (Ok(s), Ok(e)) if s < e => {
+                                self.set_view(s, e);
+                                ok()
+                            }
+                            (Ok(_), Ok(_)) => err("view start must be less than end"),
+                            (Err(e), _) | (_, Err(e)) => err(e),
+                        },
+                        "3d" => match toggle(args.next(), self.plot.is_3d) {
+                            Ok(b) => {
+                                self.plot.set_is_3d(b);
+                                self.regenerate();
+                                ok()
+                            }
+                            Err(e) => err(e),
+                        },
+                        "domain" => match toggle(args.next(), self.data.domain) {
+                            Ok(b) => {
+                                self.plot.set_mode(if b {
+                                    GraphMode::DomainColoring
+                                } else {
+                                    GraphMode::Normal
+                                });
+                                self.data.domain = b;
+                                ok()
+                            }
+                            Err(e) => err(e),
+                        },
+                        "png" => {
+                            let (w, h) = self.data.options.window_size;
+                            let path = args.next();
+                            let w = args.next().map(|a| a.parse()).unwrap_or(Ok(w as u32));
+                            let h = args.next().map(|a| a.parse()).unwrap_or(Ok(h as u32));
+                            match (w, h) {
+                                (Ok(w), Ok(h)) => self.export_png(path, w, h),
+                                _ => err("expected png <path> [width height]"),
+                            }
+                        }
+                        "csv" => {
+                            if arg.is_empty() {
+                                err("expected csv <path>")
+                            } else {
+                                let mut graph = Vec::new();
+                                self.data.generate(&mut graph);
+                                let grids = (0..graph.len())
+                                    .map(|i| self.data.grid(i))
+                                    .collect::<Vec<Option<(usize, usize)>>>();
+                                match std::fs::write(arg, csv(&graph, &grids)) {
+                                    Ok(()) => ok(),
+                                    Err(e) => err(e),
+                                }
+                            }
+                        }
+                        "value" => {
+                            let pos = match (args.next(), args.next()) {
+                                (None, _) => self
+                                    .plot
+                                    .ruler_pos
+                                    .map(|p| (p.x, p.y))
+                                    .ok_or("no position given and no ruler placed".to_owned()),
+                                (x, y) => float(x).and_then(|x| {
+                                    Ok((x, y.map(|y| float(Some(y))).transpose()?.unwrap_or(0.0)))
+                                }),
+                            };
+                            match pos {
+                                Ok((x, y)) => {
+                                    let values = self
+                                        .data
+                                        .value(x, y)
+                                        .iter()
+                                        .map(|c| {
+                                            c.map(|c| {
+                                                let (re, im) = parts(&c);
+                                                format!("[{},{}]", num(re), num(im))
+                                            })
+                                            .unwrap_or("null".to_owned())
+                                        })
+                                        .collect::<Vec<String>>()
+                                        .join(",");
+                                    format!(
+                                        "{{\"ok\":true,\"x\":{},\"y\":{},\"values\":[{values}]}}",
+                                        num(x),
+                                        num(y)
+                                    )
+                                }
+                                Err(e) => err(e),
+                            }
+                        }
+                        "budget" => match float(args.next()) {
+                            Ok(ms) if ms >= 0.0 => {
+                                self.data.budget.frame = ms / 1000.0;
+                                ok()
+                            }
+                            Ok(_) => err("frame time must not be negative"),
+                            Err(e) => err(e),
+                        },
+                        "limit" => {
+                            let i = args.next().and_then(|a| a.parse::<usize>().ok());
+                            match (i, float(args.next()), float(args.next().or(Some("1")))) {
+                                (None, _, _) => err("expected limit <function> <min> [max]"),
+                                (Some(i), Ok(min), Ok(max))
+                                    if 0.0 < min && min <= max && max <= 1.0 =>
+                                {
+                                    match self.data.data.get_mut(i).and_then(Option::as_mut) {
+                                        Some(plot) => {
+                                            plot.limit = Limit { min, max };
+                                            ok()
+                                        }
+                                        None => err(format!("no plotted function {i}")),
+                                    }
+                                }
+                                (_, Ok(_), Ok(_)) => err("expected 0 < min <= max <= 1"),
+                                (_, Err(e), _) | (_, _, Err(e)) => err(e),
+                            }
+                        }
+                        "close" => {
+                            close = true;
+                            ok()
+                        }
+                        _ => err(format!("unknown command {command}")),
+                    };
+                    let _ = reply.send(ret);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    keep = false;
+                    break;
+                }
+            }
+        }
+        if keep {
+            self.control = Some(control);
+        }
+        (name, close)
+    }
+    fn regenerate(&mut self) {
+        let mut graph = Vec::new();
+        let complex = self.data.generate(&mut graph);
+        self.plot.set_data(graph);
+        self.plot.set_is_complex(complex);
+    }
+    fn set_view(&mut self, start: f64, end: f64) {
+        self.data.options.xr = (start, end);
+        let mut graph = Vec::new();
+        let complex = self.data.generate(&mut graph);
+        let names = std::mem::take(&mut self.plot.names);
+        let mut plot = Graph::new(graph, names, complex, start, end);
+        plot.set_is_3d(self.plot.is_3d);
+        plot.is_complex = complex;
+        plot.mult = self.plot.mult;
+        plot.domain_alternate = self.plot.domain_alternate;
+        plot.main_colors = std::mem::take(&mut self.plot.main_colors);
+        plot.alt_colors = std::mem::take(&mut self.plot.alt_colors);
+        self.replace_plot(plot);
+    }
+    #[cfg(any(feature = "skia", feature = "tiny-skia"))]
+    fn export_png(&mut self, path: Option<&str>, width: u32, height: u32) -> String {
+        let Some(path) = path else {
+            return err("expected png <path> [width height]");
+        };
+        match std::fs::write(path, self.png(width, height)) {
+            Ok(()) => ok(),
+            Err(e) => err(e),
+        }
+    }
+    #[cfg(not(any(feature = "skia", feature = "tiny-skia")))]
+    fn export_png(&mut self, _: Option<&str>, _: u32, _: u32) -> String {
+        err("png export needs the skia or tiny-skia backend")
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn csv_grid() {
+        //a 2 by 1 grid, 3 samples across and 2 down
+        let grid = GraphData::Width3D(
+            (0..6).map(|i| Complex::Real(i as f64)).collect(),
+            0.0,
+            0.0,
+            2.0,
+            1.0,
+        );
+        let out = csv(&[grid], &[Some((2, 1))]);
+        let rows = out.lines().skip(1).collect::<Vec<&str>>();
+        assert_eq!(rows[2], "0,2,0,2,0");
+        assert_eq!(rows[3], "0,0,1,3,0");
+        assert_eq!(rows[5], "0,2,1,5,0");
+    }
+    #[cfg(unix)]
+    #[test]
+    fn refuses_files() {
+        let path = std::env::temp_dir().join(format!("kalc-plot-test-{}", std::process::id()));
+        std::fs::write(&path, "keep").unwrap();
+        assert!(spawn(path.to_str().unwrap().to_owned(), || {}).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
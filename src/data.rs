@@ -1,12 +1,30 @@
 #[cfg(not(feature = "rayon"))]
 use crate::IntoIter;
+#[cfg(feature = "kalc-lib")]
+use crate::branch::sheet;
+#[cfg(feature = "kalc-lib")]
+use crate::compile::{Compiled, compile};
+#[cfg(feature = "kalc-lib")]
+use crate::eval::{Tokens, Value};
+#[cfg(feature = "kalc-lib")]
+use crate::eval::{deep, floor, sample_bits};
 use crate::get_names;
 #[cfg(feature = "kalc-lib")]
+use crate::path::{PATH, Winding, trace};
+#[cfg(feature = "kalc-lib")]
+use crate::sequence::{Recurrence, Sequence, explicit, mentions, substitute};
+#[cfg(feature = "kalc-lib")]
+use crate::singular::Found;
+#[cfg(feature = "kalc-lib")]
 use crate::{C, F, I};
+#[cfg(feature = "rug")]
+use crate::{RC, RF, RI};
 #[cfg(feature = "kalc-lib")]
 use kalc_lib::complex::NumStr;
 #[cfg(feature = "kalc-lib")]
 use kalc_lib::complex::NumStr::{Matrix, Num, Vector};
+#[cfg(feature = "rug")]
+use kalc_lib::load_vars::get_vars;
 #[cfg(feature = "kalc-lib")]
 use kalc_lib::load_vars::set_commands_or_vars;
 #[cfg(feature = "kalc-lib")]
@@ -16,20 +34,27 @@ use kalc_lib::misc::{place_funcvar, place_var};
 #[cfg(feature = "kalc-lib")]
 use kalc_lib::options::silent_commands;
 #[cfg(feature = "kalc-lib")]
-use kalc_lib::parse::simplify;
-#[cfg(feature = "kalc-lib")]
 use kalc_lib::types::{Complex as Comp, Float, FloatShared};
 #[cfg(feature = "kalc-lib")]
-use kalc_lib::units::{Colors, HowGraphing, Number, Options, Variable};
+use kalc_lib::units::{Colors, GraphType, HowGraphing, Number, Options, Variable};
 #[cfg(feature = "rayon")]
 use rayon::iter::IndexedParallelIterator;
 #[cfg(feature = "rayon")]
 use rayon::iter::IntoParallelIterator;
 #[cfg(feature = "rayon")]
 use rayon::iter::ParallelIterator;
+#[cfg(feature = "kalc-lib")]
+use rupl::types::GraphMode;
 use rupl::types::{Bound, Complex, Graph, GraphData, Prec};
+#[cfg(feature = "kalc-lib")]
+use rupl::types::{Name, Show};
 #[cfg(feature = "bincode")]
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "kalc-lib")]
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "rug")]
+use std::sync::{Arc, OnceLock};
 #[cfg(not(feature = "kalc-lib"))]
 #[derive(Default, Copy, Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "bincode", derive(Serialize, Deserialize))]
@@ -115,6 +140,20 @@ pub(crate) enum Val {
     #[cfg(feature = "kalc-lib")]
     List,
 }
+//a sample coordinate kept as start plus offset so high precision evaluation can add them exactly
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Coord {
+    pub(crate) start: f64,
+    pub(crate) offset: f64,
+}
+impl Coord {
+    pub(crate) fn new(start: f64, offset: f64) -> Self {
+        Self { start, offset }
+    }
+    pub(crate) fn get(&self) -> f64 {
+        self.start + self.offset
+    }
+}
 
 #[cfg_attr(feature = "bincode", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug)]
@@ -125,6 +164,38 @@ pub(crate) struct Plot {
     #[allow(clippy::type_complexity)]
     pub(crate) funcvar: Vec<(String, Vec<NumStr<I, F, C>>)>,
     pub(crate) graph_type: Type,
+    #[cfg(feature = "kalc-lib")]
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    pub(crate) compiled: Option<Compiled>,
+    #[cfg(feature = "rug")]
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    pub(crate) precise: Precise,
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    pub(crate) cost: Cost,
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    pub(crate) limit: Limit,
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    pub(crate) sampling: Sampling,
+    #[cfg(feature = "kalc-lib")]
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    pub(crate) sequence: Option<Sequence>,
+    #[cfg(feature = "kalc-lib")]
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    pub(crate) winding: Winding,
+    #[cfg(feature = "kalc-lib")]
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    pub(crate) singularities: Found,
+    //sheets of a multivalued function after the principal one
+    #[cfg(feature = "kalc-lib")]
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    pub(crate) branches: Vec<Tokens>,
+    #[cfg(feature = "kalc-lib")]
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    pub(crate) failed: Failed,
+    //drawn as its path through the complex plane, from {argand} or the side menu
+    #[cfg(feature = "kalc-lib")]
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    pub(crate) argand: bool,
 }
 
 #[cfg(not(feature = "kalc-lib"))]
@@ -149,18 +220,329 @@ impl Options {
         }
     }
 }
+//3d grids are drawn coarse first and refined while the view is idle once sampling them is measured
+//to take longer than a frame, or before anything is measured when they have this many points
+const PROGRESSIVE: usize = 4096;
+//the first pass samples 1/2^STAGES of the grid along each axis
+const STAGES: u32 = 3;
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Refine {
+    bound: (f64, f64, f64, f64),
+    mult: f64,
+    shift: u32,
+}
+//sampling time to aim for in one frame while the view is moving
+const FRAME: f64 = 1.0 / 60.0;
+//seconds per sample of a function averaged over recent passes, written from the sampling threads
+#[derive(Debug, Default)]
+pub(crate) struct Cost(AtomicU64);
+impl Clone for Cost {
+    fn clone(&self) -> Self {
+        Self(AtomicU64::new(self.0.load(Ordering::Relaxed)))
+    }
+}
+impl Cost {
+    pub(crate) fn get(&self) -> f64 {
+        f64::from_bits(self.0.load(Ordering::Relaxed))
+    }
+    fn record(&self, seconds: f64, samples: usize) {
+        let sample = seconds / samples.max(1) as f64;
+        let old = self.get();
+        let new = if old == 0.0 {
+            sample
+        } else {
+            (old + sample) / 2.0
+        };
+        self.0.store(new.to_bits(), Ordering::Relaxed)
+    }
+    fn time<T>(&self, samples: usize, f: impl FnOnce() -> T) -> T {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let start = std::time::Instant::now();
+            let ret = f();
+            self.record(start.elapsed().as_secs_f64(), samples);
+            ret
+        }
+        #[cfg(target_arch = "wasm32")]
+        f()
+    }
+}
+//the first error a function hit while sampling, written from the sampling threads
+#[cfg(feature = "kalc-lib")]
+#[derive(Debug, Default)]
+pub(crate) struct Failed(Mutex<Option<String>>);
+#[cfg(feature = "kalc-lib")]
+impl Clone for Failed {
+    fn clone(&self) -> Self {
+        Self(Mutex::new(self.0.lock().unwrap().clone()))
+    }
+}
+#[cfg(feature = "kalc-lib")]
+impl Failed {
+    pub(crate) fn note(&self, value: &Value) {
+        if let Value::Error(e) = value {
+            self.0.lock().unwrap().get_or_insert_with(|| e.clone());
+        }
+    }
+    fn take(&self) -> Option<String> {
+        self.0.lock().unwrap().take()
+    }
+}
+//the fractions of its full sample count a function is kept between while the view is moving
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Limit {
+    pub(crate) min: f64,
+    pub(crate) max: f64,
+}
+impl Default for Limit {
+    fn default() -> Self {
+        Self {
+            min: 0.125,
+            max: 1.0,
+        }
+    }
+}
+//an interval a variable is sampled over, cut to the view or fixed regardless of it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Range {
+    Within(f64, f64),
+    Fixed(f64, f64),
+}
+impl Range {
+    //the bounds and sample count to use for a view, keeping the view's sample spacing when cut
+    fn apply(self, start: f64, end: f64, len: usize) -> Option<(f64, f64, usize)> {
+        match self {
+            Range::Within(a, b) => {
+                let (s, e) = (start.max(a), end.min(b));
+                (s <= e).then(|| {
+                    let len = (len as f64 * (e - s) / (end - start)).ceil() as usize;
+                    (s, e, len.max(1))
+                })
+            }
+            Range::Fixed(a, b) => Some((a, b, len)),
+        }
+    }
+    #[cfg(feature = "kalc-lib")]
+    fn contains(self, x: f64) -> bool {
+        let (Range::Within(a, b) | Range::Fixed(a, b)) = self;
+        a <= x && x <= b
+    }
+}
+//iterating the function as a map instead of plotting it
+#[cfg(feature = "kalc-lib")]
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Orbit {
+    //the staircase from a starting value, the ruler if placed
+    Cobweb(Option<f64>),
+    //the long run orbit for each value of the named parameter across the x axis
+    Bifurcation(String),
+}
+//iterating z -> f(z, c) from every point of the view, coloured by how soon it escapes
+#[cfg(feature = "kalc-lib")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Fractal {
+    pub(crate) iterations: usize,
+    pub(crate) bailout: f64,
+    //z starts at the point rather than at 0, set when f does not use c
+    pub(crate) julia: bool,
+}
+#[cfg(feature = "kalc-lib")]
+impl Default for Fractal {
+    fn default() -> Self {
+        Self {
+            iterations: 256,
+            bailout: 2.0,
+            julia: false,
+        }
+    }
+}
+//the images of the lines of a grid over the view, or of circles and rays about 0 when polar
+#[cfg(feature = "kalc-lib")]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Conformal {
+    pub(crate) lines: usize,
+    pub(crate) polar: bool,
+}
+#[cfg(feature = "kalc-lib")]
+impl Default for Conformal {
+    fn default() -> Self {
+        Self {
+            lines: 16,
+            polar: false,
+        }
+    }
+}
+//per function overrides written as a trailing {..} after the function
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Sampling {
+    pub(crate) x: Option<Range>,
+    pub(crate) y: Option<Range>,
+    pub(crate) mult: Option<f64>,
+    //draws a sequence as stems from the axis instead of dots
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) stems: bool,
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) orbit: Option<Orbit>,
+    //iterations shown by an orbit
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) steps: Option<usize>,
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) fractal: Option<Fractal>,
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) conformal: Option<Conformal>,
+    //points of a path whose image under the function is drawn beside it
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) path: Option<Vec<(f64, f64)>>,
+    //rings of latitude drawing the function over the riemann sphere
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) sphere: Option<usize>,
+    //draws f of real x as the path (re f, im f) through the complex plane
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) argand: bool,
+    //sheets drawn of a multivalued function of z
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) branches: Option<usize>,
+    //samples with rug at graph_prec even when f64 could tell the samples apart
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) precise: bool,
+    //the fractions of its samples kept while the view is moving, instead of the default
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) limit: Option<Limit>,
+}
+impl Sampling {
+    fn len(&self, len: usize) -> usize {
+        self.mult
+            .map_or(len, |m| ((len as f64 * m) as usize).max(1))
+    }
+    fn range(range: Option<Range>, start: f64, end: f64, len: usize) -> Option<(f64, f64, usize)> {
+        range.map_or(Some((start, end, len)), |r| r.apply(start, end, len))
+    }
+    #[cfg(feature = "kalc-lib")]
+    fn contains(&self, x: f64, y: f64) -> bool {
+        self.x.is_none_or(|r| r.contains(x)) && self.y.is_none_or(|r| r.contains(y))
+    }
+    #[cfg(feature = "kalc-lib")]
+    fn axis(&mut self, var: &str) -> Option<&mut Option<Range>> {
+        match var {
+            "x" | "n" => Some(&mut self.x),
+            "y" => Some(&mut self.y),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug)]
+enum Restore {
+    Width(f64, f64, f64),
+    Width3D(f64, f64, f64, f64, f64),
+}
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Budget {
+    //target seconds per frame, 0 always samples at full quality
+    pub(crate) frame: f64,
+    //seconds each function may take this pass, only set while the view is moving
+    share: Option<f64>,
+    restore: Option<Restore>,
+    //zeros and poles are still to be found on the grids last drawn, left for an idle frame
+    #[cfg(feature = "kalc-lib")]
+    locate: bool,
+}
+impl Default for Budget {
+    fn default() -> Self {
+        Self {
+            frame: FRAME,
+            share: None,
+            restore: None,
+            #[cfg(feature = "kalc-lib")]
+            locate: false,
+        }
+    }
+}
+impl Budget {
+    fn share(&self, functions: usize) -> Option<f64> {
+        (self.frame > 0.0).then(|| self.frame / functions.max(1) as f64)
+    }
+    fn fraction(&self, data: &Plot, samples: usize) -> f64 {
+        let cost = data.cost.get();
+        match self.share {
+            Some(share) if cost > 0.0 => {
+                (share / (cost * samples.max(1) as f64)).clamp(data.limit.min, data.limit.max)
+            }
+            _ => 1.0,
+        }
+    }
+    fn reduces(&self, data: &[Option<Plot>], samples: usize) -> bool {
+        data.iter()
+            .flatten()
+            .any(|data| self.fraction(data, samples) < 1.0)
+    }
+    fn samples(&self, data: &Plot, len: usize) -> usize {
+        ((len as f64 * self.fraction(data, len + 1)) as usize).max(1)
+    }
+    fn grid(&self, data: &Plot, lenx: usize, leny: usize) -> (usize, usize) {
+        let f = self.fraction(data, (lenx + 1) * (leny + 1)).sqrt();
+        (
+            ((lenx as f64 * f) as usize).max(1),
+            ((leny as f64 * f) as usize).max(1),
+        )
+    }
+}
 #[cfg_attr(feature = "bincode", derive(Serialize, Deserialize))]
 #[derive(Clone, Debug)]
 pub(crate) struct Data {
     pub(crate) data: Vec<Option<Plot>>,
+    #[cfg(feature = "kalc-lib")]
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    pub(crate) errors: Vec<Option<FunctionError>>,
+    #[cfg(feature = "kalc-lib")]
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    pub(crate) deep: Option<u32>,
+    //the view is as narrow as its f64 bounds can get
+    #[cfg(feature = "kalc-lib")]
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    pub(crate) floor: bool,
+    //the show each name was last given, so a change from the side menu can be told apart
+    #[cfg(feature = "kalc-lib")]
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    pub(crate) shown: Vec<Show>,
+    #[cfg(feature = "kalc-lib")]
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    pub(crate) sources: Vec<(Vec<String>, String)>,
+    #[cfg(feature = "kalc-lib")]
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    pub(crate) changes: Option<Vec<Option<usize>>>,
+    #[cfg(feature = "kalc-lib")]
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    pub(crate) ruler: Option<f64>,
+    //whether the plot is domain coloured now, the startup graphtype stops saying so once the mode
+    //is toggled over the control socket or by a keybind
+    #[cfg(feature = "kalc-lib")]
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    pub(crate) domain: bool,
     pub(crate) options: Options,
     #[cfg(feature = "kalc-lib")]
     pub(crate) vars: Vec<Variable<I, F, C>>,
     pub(crate) blacklist: Vec<usize>,
     pub(crate) var: rupl::types::Vec2,
     pub(crate) count_changed: bool,
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    pub(crate) refine: Option<Refine>,
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    pub(crate) budget: Budget,
+    //a session command in the functions menu, run once committed
+    #[cfg(feature = "bincode")]
+    #[cfg(feature = "kalc-lib")]
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    pub(crate) session: Option<crate::session::Command>,
 }
 impl Data {
+    fn progressive(&self, samples: usize) -> bool {
+        let costs = self.data.iter().flatten().map(|d| d.cost.get());
+        if costs.clone().any(|c| c == 0.0) {
+            samples >= PROGRESSIVE
+        } else {
+            costs.sum::<f64>() * samples as f64 > FRAME
+        }
+    }
     pub(crate) fn update(&mut self, plot: &mut Graph) -> Option<String> {
         #[cfg(feature = "kalc-lib")]
         let mut names = None;
@@ -174,12 +556,125 @@ impl Data {
         if plot.is_name_modified() {
             self.update_name(plot, &mut names, &mut ret);
         }
+        let mut updated = false;
         while let Some((bound, n)) = plot.update_res() {
             //TODO not optimal to compute multiple times
+            self.budget.share = if n.is_none() {
+                self.budget.share(self.data.iter().flatten().count())
+            } else {
+                None
+            };
             self.update_data(plot, &names, bound, n);
+            updated = true;
+        }
+        self.budget.share = None;
+        #[cfg(feature = "kalc-lib")]
+        if plot.ruler_pos.map(|p| p.x) != self.ruler {
+            self.ruler = plot.ruler_pos.map(|p| p.x);
+            self.update_cobwebs(plot);
+        }
+        if !updated && !self.restore(plot) && !self.refine(plot) {
+            #[cfg(feature = "kalc-lib")]
+            self.settle(plot);
+        }
+        #[cfg(feature = "kalc-lib")]
+        self.sampling_errors();
+        #[cfg(feature = "kalc-lib")]
+        {
+            self.shown = plot
+                .names
+                .iter()
+                .filter(|n| !n.name.is_empty())
+                .map(|n| n.show)
+                .collect();
         }
         ret
     }
+    //the first error each function hit while sampling, for those whose text parsed
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) fn sampling_errors(&mut self) {
+        for (i, (data, error)) in self.data.iter().zip(self.errors.iter_mut()).enumerate() {
+            if let Some(data) = data
+                && let Some(message) = data.failed.take()
+                && error.is_none()
+            {
+                *error = Some(FunctionError {
+                    function: self
+                        .sources
+                        .get(i)
+                        .map(|(_, n)| n.clone())
+                        .unwrap_or_default(),
+                    message,
+                    position: None,
+                })
+            }
+        }
+    }
+    pub(crate) fn pending(&self) -> bool {
+        #[cfg(feature = "kalc-lib")]
+        if self.budget.locate {
+            return true;
+        }
+        self.refine.is_some() || self.budget.restore.is_some()
+    }
+    //resamples at full quality after the view stops moving, returns whether it had to
+    pub(crate) fn restore(&mut self, plot: &mut Graph) -> bool {
+        let Some(restore) = self.budget.restore.take() else {
+            return false;
+        };
+        let bound = match restore {
+            Restore::Width(s, e, p) => Bound::Width(s, e, Prec::Mult(p)),
+            Restore::Width3D(sx, sy, ex, ey, p) => Bound::Width3D(sx, sy, ex, ey, Prec::Mult(p)),
+        };
+        self.update_data(plot, &None, bound, None);
+        true
+    }
+    //runs the next refinement pass of a progressive 3d plot, returns whether there was one
+    pub(crate) fn refine(&mut self, plot: &mut Graph) -> bool {
+        let Some(Refine {
+            bound: (sx, sy, ex, ey),
+            mult,
+            shift,
+        }) = self.refine.take()
+        else {
+            return false;
+        };
+        let (lx, ly) = self.samples_3d(mult);
+        let mut buffer = plot.take_data();
+        let complex = self.generate_3d(sx, sy, ex, ey, lx >> shift, ly >> shift, None, &mut buffer);
+        plot.set_data(buffer);
+        plot.is_complex |= complex;
+        #[cfg(feature = "kalc-lib")]
+        {
+            self.budget.locate = true;
+        }
+        if shift > 0 {
+            self.refine = Some(Refine {
+                bound: (sx, sy, ex, ey),
+                mult,
+                shift: shift - 1,
+            })
+        }
+        true
+    }
+    //finds zeros and poles on the grids last drawn once there is nothing left to sample, returns
+    //whether it had to
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) fn settle(&mut self, plot: &mut Graph) -> bool {
+        if !std::mem::take(&mut self.budget.locate) {
+            return false;
+        }
+        let mut buffer = plot.take_data();
+        self.locate(&mut buffer);
+        plot.set_data(buffer);
+        true
+    }
+    fn samples_3d(&self, mult: f64) -> (usize, usize) {
+        (
+            (mult * self.options.samples_3d.0 as f64) as usize,
+            (mult * self.options.samples_3d.1 as f64) as usize,
+        )
+    }
     pub(crate) fn update_data(
         &mut self,
         plot: &mut Graph,
@@ -188,6 +683,14 @@ impl Data {
         k: Option<usize>,
     ) {
         self.var = plot.var;
+        #[cfg(feature = "kalc-lib")]
+        {
+            self.domain = matches!(plot.graph_mode, GraphMode::DomainColoring);
+        }
+        if k.is_none() {
+            self.refine = None;
+            self.budget.restore = None;
+        }
         self.blacklist = plot
             .blacklist_graphs
             .iter()
@@ -197,10 +700,38 @@ impl Data {
             .into_iter()
             .filter_map(|i| match plot.index_to_name(i, false) {
                 (Some(a), None) => Some(a),
-                (None, Some((_, _))) => None, //TODO should not discard, but would have to update all after
+                (None, Some((_, _))) => None,
                 _ => None,
             })
             .next();
+        #[cfg(feature = "kalc-lib")]
+        {
+            self.deep = deep(match &bound {
+                Bound::Width(s, e, _) => sample_bits(*s, *e, self.options.samples_2d),
+                Bound::Width3D(sx, sy, ex, ey, _) => sample_bits(
+                    *sx,
+                    *ex,
+                    self.options.samples_3d.0,
+                )
+                .max(sample_bits(*sy, *ey, self.options.samples_3d.1)),
+            });
+            self.floor = match &bound {
+                Bound::Width(s, e, _) => floor(*s, *e),
+                Bound::Width3D(sx, sy, ex, ey, _) => floor(*sx, *ex) || floor(*sy, *ey),
+            };
+        }
+        #[cfg(feature = "kalc-lib")]
+        let changes = self.changes.take();
+        #[cfg(feature = "kalc-lib")]
+        let (targets, count_changed) = if k.is_some()
+            && let Some(changes) = changes
+        {
+            (restructure(plot, changes), false)
+        } else {
+            (vec![n], self.count_changed)
+        };
+        #[cfg(not(feature = "kalc-lib"))]
+        let (targets, count_changed) = (vec![n], self.count_changed);
         let apply_names =
             |data: &[GraphData], complex: bool, plot: &mut Graph, k: Option<usize>| {
                 if let Some(names) = names {
@@ -223,87 +754,129 @@ impl Data {
                     plot.is_complex |= complex;
                 }
             };
-        match bound {
-            Bound::Width(s, e, Prec::Mult(p)) => {
-                let mut get = || {
-                    let mut buffer = if let Some(n) = n {
-                        vec![plot.remove_data(n)]
-                    } else {
-                        plot.take_data()
+        for n in targets {
+            match &bound {
+                &Bound::Width(s, e, Prec::Mult(p)) => {
+                    let len = (p * self.options.samples_2d as f64) as usize;
+                    if self.budget.reduces(&self.data, len + 1) {
+                        self.budget.restore = Some(Restore::Width(s, e, p))
+                    }
+                    let mut get = || {
+                        let mut buffer = if let Some(n) = n {
+                            vec![plot.remove_data(n)]
+                        } else {
+                            plot.take_data()
+                        };
+                        let complex = self.generate_2d(s, e, len, n, &mut buffer);
+                        apply_names(&buffer, complex, plot, n);
+                        buffer
                     };
-                    let complex = self.generate_2d(
-                        s,
-                        e,
-                        (p * self.options.samples_2d as f64) as usize,
-                        n,
-                        &mut buffer,
-                    );
-                    apply_names(&buffer, complex, plot, n);
-                    buffer
-                };
-                if let Some(n) = n {
-                    if self.count_changed {
-                        plot.remove_data(n);
+                    if let Some(n) = n {
+                        if count_changed {
+                            plot.remove_data(n);
+                        } else {
+                            let data = get();
+                            plot.insert_data(data.into_iter().next().unwrap(), n);
+                        }
                     } else {
                         let data = get();
-                        plot.insert_data(data.into_iter().next().unwrap(), n);
+                        plot.set_data(data);
                     }
-                } else {
-                    let data = get();
-                    plot.set_data(data);
-                }
-            }
-            Bound::Width3D(sx, sy, ex, ey, p) => {
-                if n.is_none() {
-                    plot.clear_data();
                 }
-                let mut get = || {
-                    let mut buffer = if let Some(n) = n {
-                        vec![plot.remove_data(n)]
-                    } else {
-                        plot.take_data()
-                    };
-                    let complex = match p {
-                        Prec::Mult(p) => {
-                            let lx = (p * self.options.samples_3d.0 as f64) as usize;
-                            let ly = (p * self.options.samples_3d.1 as f64) as usize;
-                            self.generate_3d(sx, sy, ex, ey, lx, ly, n, &mut buffer)
-                        }
-                        Prec::Dimension(x, y) => {
-                            self.generate_3d(sx, sy, ex, ey, x - 1, y - 1, n, &mut buffer)
+                &Bound::Width3D(sx, sy, ex, ey, ref p) => {
+                    if n.is_none() {
+                        plot.clear_data();
+                    }
+                    let shift = match p {
+                        Prec::Mult(p) if n.is_none() => {
+                            let (lx, ly) = self.samples_3d(*p);
+                            if self.progressive((lx + 1) * (ly + 1)) {
+                                STAGES
+                            } else {
+                                0
+                            }
                         }
-                        Prec::Slice(p) => {
-                            let l = (p * self.options.samples_2d as f64) as usize;
-                            self.generate_2d_slice(
-                                sx,
-                                sy,
-                                ex,
-                                ey,
-                                l,
-                                l,
-                                plot.slice,
-                                plot.view_x,
-                                n,
-                                &mut buffer,
-                            )
+                        _ => 0,
+                    };
+                    if let Prec::Mult(p) = p {
+                        let (lx, ly) = self.samples_3d(*p);
+                        if self
+                            .budget
+                            .reduces(&self.data, ((lx >> shift) + 1) * ((ly >> shift) + 1))
+                        {
+                            self.budget.restore = Some(Restore::Width3D(sx, sy, ex, ey, *p))
                         }
+                    }
+                    let mut get = || {
+                        let mut buffer = if let Some(n) = n {
+                            plot.remove_data(n);
+                            vec![GraphData::None]
+                        } else {
+                            plot.take_data()
+                        };
+                        let complex = match p {
+                            Prec::Mult(p) => {
+                                let (lx, ly) = self.samples_3d(*p);
+                                self.generate_3d(
+                                    sx,
+                                    sy,
+                                    ex,
+                                    ey,
+                                    lx >> shift,
+                                    ly >> shift,
+                                    n,
+                                    &mut buffer,
+                                )
+                            }
+                            Prec::Dimension(x, y) => {
+                                self.generate_3d(sx, sy, ex, ey, *x - 1, *y - 1, n, &mut buffer)
+                            }
+                            Prec::Slice(p) => {
+                                let l = (p * self.options.samples_2d as f64) as usize;
+                                self.generate_2d_slice(
+                                    sx,
+                                    sy,
+                                    ex,
+                                    ey,
+                                    l,
+                                    l,
+                                    plot.slice,
+                                    plot.view_x,
+                                    n,
+                                    &mut buffer,
+                                )
+                            }
+                        };
+                        apply_names(&buffer, complex, plot, n);
+                        buffer
                     };
-                    apply_names(&buffer, complex, plot, n);
-                    buffer
-                };
-                if let Some(n) = n {
-                    if self.count_changed {
-                        plot.remove_data(n);
+                    if let Some(n) = n {
+                        if count_changed {
+                            plot.remove_data(n);
+                        } else {
+                            let data = get();
+                            plot.insert_data(data.into_iter().next().unwrap(), n);
+                        }
                     } else {
                         let data = get();
-                        plot.insert_data(data.into_iter().next().unwrap(), n);
+                        plot.set_data(data);
+                    }
+                    #[cfg(feature = "kalc-lib")]
+                    {
+                        self.budget.locate = true;
+                    }
+                    if shift > 0
+                        && let Prec::Mult(mult) = p
+                    {
+                        self.refine = Some(Refine {
+                            bound: (sx, sy, ex, ey),
+                            mult: *mult,
+                            shift: shift - 1,
+                        })
                     }
-                } else {
-                    let data = get();
-                    plot.set_data(data);
                 }
+                Bound::Width(_, _, _) => unreachable!(),
             }
-            Bound::Width(_, _, _) => unreachable!(),
         }
     }
     #[cfg(feature = "kalc-lib")]
@@ -316,26 +889,14 @@ impl Data {
         let Some(name) = plot.update_res_name() else {
             unreachable!()
         };
-        let mut i = 0;
-        let mut func = Vec::with_capacity(name.len());
-        for n in name {
-            let mut v: Vec<String> = Vec::with_capacity(n.vars.len());
-            for a in &n.vars {
-                if !a.is_empty() && !plot.blacklist_graphs.contains(&i) {
-                    v.push(a.clone())
-                }
-                i += 1;
-            }
-            if !n.name.is_empty() || !v.is_empty() {
-                func.push(if v.is_empty() {
-                    n.name.clone()
-                } else {
-                    format!("{};{}", v.join(";"), n.name)
-                })
-            }
-            i += 1;
+        #[cfg(feature = "bincode")]
+        {
+            self.session = name
+                .iter()
+                .find_map(|n| crate::session::Command::parse(&n.name));
         }
-        let func = func.join("#").replace(";#", ";");
+        let toggled = self.toggle_argand(name);
+        let func = join_function(name, &plot.blacklist_graphs);
         #[cfg(feature = "wasm")]
         if !plot.is_drag() {
             use base64::{Engine as _, engine::general_purpose::URL_SAFE};
@@ -343,13 +904,16 @@ impl Data {
             let hash = format!("#{}", URL_SAFE.encode(data));
             crate::set_hash(&hash);
         }
-        let new_name;
         let old_len = self.data.len();
-        (self.data, new_name, _) = init(&func, &mut self.options, self.vars.clone()).unwrap_or((
-            Vec::new(),
-            Vec::new(),
-            HowGraphing::default(),
-        ));
+        let (functions, _) = init_from(&func, &mut self.options, self.vars.clone(), &self.sources);
+        self.changes = Some(
+            functions
+                .iter()
+                .enumerate()
+                .map(|(i, f)| f.reuse.filter(|_| !toggled.contains(&i)))
+                .collect(),
+        );
+        let new_name = self.set_functions(functions);
         self.count_changed = old_len != self.data.len();
         if !new_name.is_empty() || name.is_empty() {
             *names = Some(new_name);
@@ -357,6 +921,34 @@ impl Data {
         plot.set_is_3d(self.is_3d());
         *ret = Some(func);
     }
+    //switching a complex function of x from complex to real in the side menu draws it as its
+    //argand path, which is a real curve so its name stays real until switched back, returns
+    //the functions switched
+    #[cfg(feature = "kalc-lib")]
+    fn toggle_argand(&mut self, names: &[Name]) -> Vec<usize> {
+        let names = names.iter().filter(|n| !n.name.is_empty());
+        let mut toggled = Vec::new();
+        for (i, ((data, shown), name)) in
+            self.data.iter_mut().zip(&self.shown).zip(names).enumerate()
+        {
+            let Some(data) = data else {
+                continue;
+            };
+            if data.graph_type.inv() || data.graph_type.is_3d_o() {
+                continue;
+            }
+            let argand = match (shown, name.show) {
+                (Show::Complex, Show::Real) => true,
+                (Show::Real, Show::Complex | Show::Imag) if data.argand => false,
+                _ => continue,
+            };
+            if data.argand != argand {
+                data.argand = argand;
+                toggled.push(i)
+            }
+        }
+        toggled
+    }
     #[cfg(feature = "kalc-lib")]
     pub(crate) fn is_3d(&self) -> bool {
         self.data.iter().any(|d| {
@@ -366,22 +958,214 @@ impl Data {
                 .unwrap_or(false)
         })
     }
-    #[allow(clippy::too_many_arguments)]
-    pub(crate) fn generate_3d(
-        &self,
-        startx: f64,
-        starty: f64,
-        endx: f64,
-        endy: f64,
-        lenx: usize,
-        leny: usize,
-        n: Option<usize>,
-        buffer: &mut Vec<GraphData>,
-    ) -> bool {
-        let len = if n.is_some() { 1 } else { self.data.len() };
-        if len > buffer.len() {
-            for _ in 0..len - buffer.len() {
-                buffer.push(GraphData::None)
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) fn set_functions(&mut self, functions: Vec<Function>) -> Vec<(Vec<String>, String)> {
+        let mut old = std::mem::take(&mut self.data)
+            .into_iter()
+            .zip(std::mem::take(&mut self.errors))
+            .map(Some)
+            .collect::<Vec<Option<(Option<Plot>, Option<FunctionError>)>>>();
+        self.data = Vec::with_capacity(functions.len());
+        self.errors = Vec::with_capacity(functions.len());
+        //a limit set from the control socket and argand from the side menu come along with a reused
+        //plot, and stay with an edited function at its index unless that old function moved
+        let limits = old
+            .iter()
+            .map(|o| {
+                let plot = o.as_ref()?.0.as_ref()?;
+                plot.sampling.limit.is_none().then_some(plot.limit)
+            })
+            .collect::<Vec<Option<Limit>>>();
+        let argand = old
+            .iter()
+            .map(|o| {
+                let plot = o.as_ref()?.0.as_ref()?;
+                (!plot.sampling.argand).then_some(plot.argand)
+            })
+            .collect::<Vec<Option<bool>>>();
+        let moved = functions
+            .iter()
+            .filter_map(|f| f.reuse)
+            .collect::<Vec<usize>>();
+        let names = functions
+            .into_iter()
+            .enumerate()
+            .map(|(i, f)| {
+                let from = f.reuse.or((!moved.contains(&i)).then_some(i));
+                let reuse = f.reuse.and_then(|i| old.get_mut(i)?.take());
+                let (mut plot, name, error) = f.into_parts();
+                if let Some(plot) = &mut plot
+                    && plot.sampling.limit.is_none()
+                    && let Some(Some(limit)) = from.and_then(|i| limits.get(i))
+                {
+                    plot.limit = *limit
+                }
+                if let Some(plot) = &mut plot
+                    && !plot.sampling.argand
+                    && let Some(Some(argand)) = from.and_then(|i| argand.get(i))
+                {
+                    plot.argand = *argand
+                }
+                let (plot, error) = reuse.unwrap_or((plot, error));
+                self.data.push(plot);
+                self.errors.push(error);
+                name
+            })
+            .collect::<Vec<(Vec<String>, String)>>();
+        self.sources = names.clone();
+        names
+    }
+    //rebuilds what sessions do not store from the plots and the function text they came from
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) fn compile(&mut self, function: &str) {
+        for plot in self.data.iter_mut().flatten() {
+            plot.compiled = compile(&plot.func, &plot.funcvar, &self.options)
+        }
+        let mut options = self.options;
+        let (functions, _) = init(function, &mut options, self.vars.clone());
+        for (plot, f) in self.data.iter_mut().zip(functions) {
+            if let Some(plot) = plot {
+                if let Some(limit) = f.sampling.limit {
+                    plot.limit = limit
+                }
+                plot.argand |= f.sampling.argand;
+                plot.sampling = f.sampling;
+                plot.sequence = f.sequence;
+                plot.branches = f.branches;
+                #[cfg(feature = "rug")]
+                {
+                    plot.precise = f.precise;
+                }
+            }
+        }
+    }
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) fn set_function(&mut self, plot: &mut Graph, function: &str) {
+        let (functions, _) = init(function, &mut self.options, self.vars.clone());
+        let names = self.set_functions(functions);
+        self.count_changed = false;
+        plot.set_is_3d(self.is_3d());
+        let mut graph = Vec::new();
+        let complex = self.generate(&mut graph);
+        plot.names = get_names(&graph, &names);
+        plot.set_data(graph);
+        plot.set_is_complex(complex);
+    }
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) fn generate(&self, graph: &mut Vec<GraphData>) -> bool {
+        if self.is_3d() {
+            self.generate_3d(
+                self.options.xr.0,
+                self.options.yr.0,
+                self.options.xr.1,
+                self.options.yr.1,
+                self.options.samples_3d.0,
+                self.options.samples_3d.1,
+                None,
+                graph,
+            )
+        } else {
+            self.generate_2d(
+                self.options.xr.0,
+                self.options.xr.1,
+                self.options.samples_2d,
+                None,
+                graph,
+            )
+        }
+    }
+    //the samples along each axis of function i's grid as generate draws it
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) fn grid(&self, i: usize) -> Option<(usize, usize)> {
+        let data = self.data.get(i)?.as_ref()?;
+        let (lenx, leny) = self.options.samples_3d;
+        let (xr, yr) = (self.options.xr, self.options.yr);
+        let (_, _, lenx) = Sampling::range(data.sampling.x, xr.0, xr.1, data.sampling.len(lenx))?;
+        let (_, _, leny) = Sampling::range(data.sampling.y, yr.0, yr.1, data.sampling.len(leny))?;
+        Some((lenx, leny))
+    }
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) fn sample_options(&self, bits: u32) -> Options {
+        if let Some(bits) = deep(bits) {
+            Options {
+                prec: self.options.prec.max(bits),
+                graph_prec: self.options.graph_prec.max(bits),
+                ..self.options
+            }
+        } else {
+            self.options
+        }
+    }
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) fn precision(&self) -> Option<String> {
+        #[cfg(feature = "rug")]
+        {
+            self.deep.map(|bits| {
+                if self.floor {
+                    format!("{bits} bit, zoom limit")
+                } else {
+                    format!("{bits} bit")
+                }
+            })
+        }
+        #[cfg(not(feature = "rug"))]
+        self.deep.map(|_| "f64 limit".to_owned())
+    }
+    #[cfg(feature = "kalc-lib")]
+    pub(crate) fn value(&self, x: f64, y: f64) -> Vec<Option<Complex>> {
+        self.data
+            .iter()
+            .map(|data| {
+                let data = data.as_ref()?;
+                if !data.sampling.contains(x, y)
+                    || data.sampling.conformal.is_some()
+                    || data.sampling.path.is_some()
+                    || data.argand
+                {
+                    return None;
+                }
+                let mut buffer = GraphData::None;
+                if let Some(sequence) = &data.sequence {
+                    self.get_sequence(data, sequence, x, x, 0, &mut buffer)?;
+                    let GraphData::List(list) = buffer else {
+                        return None;
+                    };
+                    return list.iter().find_map(|d| match d {
+                        GraphData::Point(p) => Some(Complex::Real(p.y)),
+                        _ => None,
+                    });
+                }
+                if data.graph_type.is_3d_i() {
+                    self.get_3d(data, x, y, x, y, 1, 1, &mut buffer)?;
+                } else {
+                    self.get_2d(data, x, x, 1, &mut buffer)?;
+                }
+                match buffer {
+                    GraphData::Width(v, _, _) | GraphData::Width3D(v, _, _, _, _) => {
+                        v.first().copied()
+                    }
+                    GraphData::Constant(c, _) => Some(c),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn generate_3d(
+        &self,
+        startx: f64,
+        starty: f64,
+        endx: f64,
+        endy: f64,
+        lenx: usize,
+        leny: usize,
+        n: Option<usize>,
+        buffer: &mut Vec<GraphData>,
+    ) -> bool {
+        let len = if n.is_some() { 1 } else { self.data.len() };
+        if len > buffer.len() {
+            for _ in 0..len - buffer.len() {
+                buffer.push(GraphData::None)
             }
         }
         buffer.into_par_iter().enumerate().any(|(mut i, b)| {
@@ -397,15 +1181,36 @@ impl Data {
             if !data.graph_type.is_3d_o() {
                 return false;
             }
-            match (data.graph_type.is_3d_i(), data.graph_type.on_var()) {
-                (true, false) => self.get_3d(data, startx, starty, endx, endy, lenx, leny, b),
-                (true, true) => self.get_3d(
-                    data, self.var.x, self.var.x, self.var.y, self.var.y, lenx, leny, b,
-                ),
-                (false, true) => self.get_2d(data, self.var.x, self.var.y, lenx * leny, b),
-                (false, false) => None,
-            }
-            .unwrap_or(false)
+            let (lenx, leny) =
+                self.budget
+                    .grid(data, data.sampling.len(lenx), data.sampling.len(leny));
+            data.cost
+                .time((lenx + 1) * (leny + 1), || {
+                    match (data.graph_type.is_3d_i(), data.graph_type.on_var()) {
+                        (true, false) => match (
+                            Sampling::range(data.sampling.x, startx, endx, lenx),
+                            Sampling::range(data.sampling.y, starty, endy, leny),
+                        ) {
+                            (Some((sx, ex, lx)), Some((sy, ey, ly))) => {
+                                #[cfg(feature = "kalc-lib")]
+                                {
+                                    *data.singularities.grid.lock().unwrap() = Some((lx, ly));
+                                }
+                                self.get_3d(data, sx, sy, ex, ey, lx, ly, b)
+                            }
+                            _ => {
+                                *b = GraphData::None;
+                                Some(false)
+                            }
+                        },
+                        (true, true) => self.get_3d(
+                            data, self.var.x, self.var.x, self.var.y, self.var.y, lenx, leny, b,
+                        ),
+                        (false, true) => self.get_2d(data, self.var.x, self.var.y, lenx * leny, b),
+                        (false, false) => None,
+                    }
+                })
+                .unwrap_or(false)
         })
     }
     #[allow(clippy::too_many_arguments)]
@@ -422,6 +1227,29 @@ impl Data {
     ) -> Option<bool> {
         let dx = (endx - startx) / lenx as f64;
         let dy = (endy - starty) / leny as f64;
+        #[cfg(feature = "kalc-lib")]
+        let options = self
+            .sample_options(sample_bits(startx, endx, lenx).max(sample_bits(starty, endy, leny)));
+        #[cfg(feature = "kalc-lib")]
+        if let Some(rings) = data.sampling.sphere {
+            let (sphere, complex) = self.get_sphere(data, rings);
+            *buffer = sphere;
+            return Some(complex);
+        }
+        #[cfg(feature = "kalc-lib")]
+        if data.sampling.branches.is_some() {
+            return self.get_branches(
+                data, startx, starty, endx, endy, lenx, leny, options, buffer,
+            );
+        }
+        #[cfg(feature = "kalc-lib")]
+        if let Some(fractal) = &data.sampling.fractal {
+            let (a, b) = compact(
+                self.get_fractal(data, fractal, startx, starty, dx, dy, lenx, leny, options),
+            );
+            *buffer = GraphData::Width3D(a, startx, starty, endx, endy);
+            return Some(b);
+        }
         Some(match &data.graph_type.val {
             Val::Num(n) => {
                 if let Some(c) = n {
@@ -434,38 +1262,48 @@ impl Data {
                         unreachable!()
                     };
                     */
+                    //a grid of half the resolution over the same view gives every other point
+                    let prev = match buffer {
+                        GraphData::Width3D(v, sx, sy, ex, ey)
+                            if (*sx, *sy, *ex, *ey) == (startx, starty, endx, endy)
+                                && lenx % 2 == 0
+                                && leny % 2 == 0
+                                && v.len() == (lenx / 2 + 1) * (leny / 2 + 1) =>
+                        {
+                            std::mem::take(v)
+                        }
+                        _ => Vec::new(),
+                    };
+                    #[cfg(feature = "kalc-lib")]
+                    let failed = &data.failed;
                     let data = (0..=leny)
                         .into_par_iter()
                         .flat_map(|j| {
-                            let y = starty + j as f64 * dy;
-                            #[cfg(feature = "kalc-lib")]
-                            let y = NumStr::new(Number::from_f64(y, &self.options));
-                            #[cfg(feature = "kalc-lib")]
-                            let mut modified = place_var(data.func.clone(), "y", y.clone());
+                            let y = Coord::new(starty, j as f64 * dy);
                             #[cfg(feature = "kalc-lib")]
-                            let mut modifiedvars = place_funcvar(data.funcvar.clone(), "y", y);
-                            #[cfg(feature = "kalc-lib")]
-                            simplify(&mut modified, &mut modifiedvars, self.options);
+                            let modified = data.bind("y", y, options);
                             let mut data = Vec::with_capacity(lenx + 1);
                             for i in 0..=lenx {
-                                let x = startx + i as f64 * dx;
-                                #[cfg(feature = "kalc-lib")]
-                                let x = NumStr::new(Number::from_f64(x, &self.options));
+                                if i % 2 == 0
+                                    && j % 2 == 0
+                                    && let Some(c) = prev.get(j / 2 * (lenx / 2 + 1) + i / 2)
+                                {
+                                    let (re, im) = c.to_options();
+                                    data.push(Complex::Complex(
+                                        re.unwrap_or(0.0),
+                                        im.unwrap_or(0.0),
+                                    ));
+                                    continue;
+                                }
+                                let x = Coord::new(startx, i as f64 * dx);
                                 #[cfg(feature = "kalc-lib")]
-                                let v = if let Ok(Num(n)) = do_math(
-                                    place_var(modified.clone(), "x", x.clone()),
-                                    self.options,
-                                    place_funcvar(modifiedvars.clone(), "x", x),
-                                ) {
-                                    Complex::Complex(
-                                        n.number.real().to_f64(),
-                                        n.number.imag().to_f64(),
-                                    )
-                                } else {
-                                    Complex::Complex(f64::NAN, f64::NAN)
+                                let v = {
+                                    let v = modified.eval("x", x, options);
+                                    failed.note(&v);
+                                    v.complex()
                                 };
                                 #[cfg(not(feature = "kalc-lib"))]
-                                let v = f3(x, y);
+                                let v = f3(x.get(), y.get());
                                 data.push(v)
                             }
                             data
@@ -488,31 +1326,17 @@ impl Data {
                     let data = (0..=leny)
                         .into_par_iter()
                         .flat_map(|j| {
-                            let y = starty + j as f64 * dy;
-                            let y = NumStr::new(Number::from_f64(y, &self.options));
-                            let mut modified = place_var(data.func.clone(), "y", y.clone());
-                            let mut modifiedvars = place_funcvar(data.funcvar.clone(), "y", y);
-                            simplify(&mut modified, &mut modifiedvars, self.options);
+                            let y = Coord::new(starty, j as f64 * dy);
+                            let modified = data.bind("y", y, options);
                             let mut data = Vec::with_capacity(lenx + 1);
                             for i in 0..=lenx {
-                                let x = startx + i as f64 * dx;
-                                let x = NumStr::new(Number::from_f64(x, &self.options));
+                                let x = Coord::new(startx, i as f64 * dx);
                                 data.push(
-                                    if let Ok(Vector(n)) = do_math(
-                                        place_var(modified.clone(), "x", x.clone()),
-                                        self.options,
-                                        place_funcvar(modifiedvars.clone(), "x", x),
-                                    ) {
+                                    if let Value::Vector(n) = modified.eval("x", x, options) {
                                         if n.len() != 2 {
                                             (f64::NAN, Complex::Complex(f64::NAN, f64::NAN))
                                         } else {
-                                            (
-                                                n[0].number.real().to_f64(),
-                                                Complex::Complex(
-                                                    n[1].number.real().to_f64(),
-                                                    n[1].number.real().to_f64(),
-                                                ),
-                                            )
+                                            (n[0].0, Complex::Complex(n[1].0, n[1].0))
                                         }
                                     } else {
                                         (f64::NAN, Complex::Complex(f64::NAN, f64::NAN))
@@ -532,37 +1356,20 @@ impl Data {
                 let data = (0..=leny)
                     .into_par_iter()
                     .flat_map(|j| {
-                        let y = starty + j as f64 * dy;
-                        let y = NumStr::new(Number::from_f64(y, &self.options));
-                        let mut modified = place_var(data.func.clone(), "y", y.clone());
-                        let mut modifiedvars = place_funcvar(data.funcvar.clone(), "y", y);
-                        simplify(&mut modified, &mut modifiedvars, self.options);
+                        let y = Coord::new(starty, j as f64 * dy);
+                        let modified = data.bind("y", y, options);
                         let mut data = Vec::with_capacity(lenx + 1);
                         for i in 0..=lenx {
-                            let x = startx + i as f64 * dx;
-                            let x = NumStr::new(Number::from_f64(x, &self.options));
-                            data.push(
-                                if let Ok(Vector(n)) = do_math(
-                                    place_var(modified.clone(), "x", x.clone()),
-                                    self.options,
-                                    place_funcvar(modifiedvars.clone(), "x", x),
-                                ) {
-                                    if n.len() != 3 {
-                                        (f64::NAN, f64::NAN, Complex::Complex(f64::NAN, f64::NAN))
-                                    } else {
-                                        (
-                                            n[0].number.real().to_f64(),
-                                            n[1].number.real().to_f64(),
-                                            Complex::Complex(
-                                                n[2].number.real().to_f64(),
-                                                n[2].number.imag().to_f64(),
-                                            ),
-                                        )
-                                    }
-                                } else {
+                            let x = Coord::new(startx, i as f64 * dx);
+                            data.push(if let Value::Vector(n) = modified.eval("x", x, options) {
+                                if n.len() != 3 {
                                     (f64::NAN, f64::NAN, Complex::Complex(f64::NAN, f64::NAN))
-                                },
-                            )
+                                } else {
+                                    (n[0].0, n[1].0, Complex::Complex(n[2].0, n[2].1))
+                                }
+                            } else {
+                                (f64::NAN, f64::NAN, Complex::Complex(f64::NAN, f64::NAN))
+                            })
                         }
                         data
                     })
@@ -576,34 +1383,17 @@ impl Data {
                 let mut ndata: Vec<Vec<(f64, f64, Complex)>> =
                     Vec::with_capacity((leny + 1) * (lenx + 1));
                 for j in 0..=leny {
-                    let ys = starty + j as f64 * dy;
-                    let y = NumStr::new(Number::from_f64(ys, &self.options));
-                    let mut modified = place_var(data.func.clone(), "y", y.clone());
-                    let mut modifiedvars = place_funcvar(data.funcvar.clone(), "y", y);
-                    simplify(&mut modified, &mut modifiedvars, self.options);
+                    let ys = Coord::new(starty, j as f64 * dy);
+                    let modified = data.bind("y", ys, options);
                     for i in 0..=lenx {
-                        let xs = startx + i as f64 * dx;
-                        let x = NumStr::new(Number::from_f64(xs, &self.options));
-                        if let Ok(Vector(v)) = do_math(
-                            place_var(modified.clone(), "x", x.clone()),
-                            self.options,
-                            place_funcvar(modifiedvars.clone(), "x", x),
-                        ) {
+                        let xs = Coord::new(startx, i as f64 * dx);
+                        if let Value::Vector(v) = modified.eval("x", xs, options) {
                             let mut v = v.into_iter();
                             ndata.extend(vec![Vec::new(); v.len().saturating_sub(ndata.len())]);
                             for data in ndata.iter_mut() {
                                 let n = v
                                     .next()
-                                    .map(|n| {
-                                        (
-                                            xs,
-                                            ys,
-                                            Complex::Complex(
-                                                n.number.real().to_f64(),
-                                                n.number.imag().to_f64(),
-                                            ),
-                                        )
-                                    })
+                                    .map(|(re, im)| (xs.get(), ys.get(), Complex::Complex(re, im)))
                                     .unwrap_or((
                                         f64::NAN,
                                         f64::NAN,
@@ -694,9 +1484,10 @@ impl Data {
         };
         let dx = (endx - startx) / lenx as f64;
         let dy = (endy - starty) / leny as f64;
-        let xs = startx + (slice as f64 + lenx as f64 / 2.0) * dx;
+        let xs = Coord::new(startx, (slice as f64 + lenx as f64 / 2.0) * dx);
         #[cfg(feature = "kalc-lib")]
-        let x = NumStr::new(Number::from_f64(xs, &self.options));
+        let options = self
+            .sample_options(sample_bits(startx, endx, lenx).max(sample_bits(starty, endy, leny)));
         buffer.into_par_iter().enumerate().any(|(mut i, buf)| {
             if let Some(n) = n {
                 i = n;
@@ -712,34 +1503,19 @@ impl Data {
                 matches!(c, Complex::Complex(_, _) | Complex::Imag(_))
             } else {
                 #[cfg(feature = "kalc-lib")]
-                let mut modified = place_var(data.func.clone(), xstr, x.clone());
-                #[cfg(feature = "kalc-lib")]
-                let mut modifiedvars = place_funcvar(data.funcvar.clone(), xstr, x.clone());
-                #[cfg(feature = "kalc-lib")]
-                simplify(&mut modified, &mut modifiedvars, self.options);
+                let modified = data.bind(xstr, xs, options);
                 match &data.graph_type.val {
                     Val::Num(_) => {
                         let data = (0..=leny)
                             .into_par_iter()
                             .map(|i| {
-                                let y = starty + i as f64 * dy;
+                                let y = Coord::new(starty, i as f64 * dy);
                                 #[cfg(feature = "kalc-lib")]
-                                let y = NumStr::new(Number::from_f64(y, &self.options));
-                                #[cfg(feature = "kalc-lib")]
-                                if let Ok(Num(n)) = do_math(
-                                    place_var(modified.clone(), ystr, y.clone()),
-                                    self.options,
-                                    place_funcvar(modifiedvars.clone(), ystr, y),
-                                ) {
-                                    Complex::Complex(
-                                        n.number.real().to_f64(),
-                                        n.number.imag().to_f64(),
-                                    )
-                                } else {
-                                    Complex::Complex(f64::NAN, f64::NAN)
+                                {
+                                    modified.eval(ystr, y, options).complex()
                                 }
                                 #[cfg(not(feature = "kalc-lib"))]
-                                f3(xs, y)
+                                f3(xs.get(), y.get())
                             })
                             .collect::<Vec<Complex>>();
                         #[cfg(feature = "kalc-lib")]
@@ -763,27 +1539,15 @@ impl Data {
                     Val::List => {
                         let mut ndata: Vec<Vec<(f64, f64, Complex)>> = Vec::with_capacity(leny + 1);
                         for i in 0..=leny {
-                            let xv = starty + i as f64 * dx;
-                            let x = NumStr::new(Number::from_f64(xv, &self.options));
-                            if let Ok(Vector(v)) = do_math(
-                                place_var(modified.clone(), ystr, x.clone()),
-                                self.options,
-                                place_funcvar(modifiedvars.clone(), ystr, x),
-                            ) {
+                            let xv = Coord::new(starty, i as f64 * dx);
+                            if let Value::Vector(v) = modified.eval(ystr, xv, options) {
                                 let mut v = v.into_iter();
                                 ndata.extend(vec![Vec::new(); v.len().saturating_sub(ndata.len())]);
                                 for data in ndata.iter_mut() {
                                     let n = v
                                         .next()
-                                        .map(|n| {
-                                            (
-                                                xs,
-                                                xv,
-                                                Complex::Complex(
-                                                    n.number.real().to_f64(),
-                                                    n.number.imag().to_f64(),
-                                                ),
-                                            )
+                                        .map(|(re, im)| {
+                                            (xs.get(), xv.get(), Complex::Complex(re, im))
                                         })
                                         .unwrap_or((
                                             f64::NAN,
@@ -847,22 +1611,59 @@ impl Data {
             let Some(data) = &self.data[i] else {
                 return false;
             };
-            match (data.graph_type.is_3d_i(), data.graph_type.on_var()) {
-                (true, true) => self.get_3d(
-                    data,
-                    self.var.x,
-                    self.var.x,
-                    self.var.y,
-                    self.var.y,
-                    len.isqrt(),
-                    len.isqrt(),
-                    b,
-                ),
-                (false, true) => self.get_2d(data, self.var.x, self.var.y, len, b),
-                (false, false) => self.get_2d(data, start, end, len, b),
-                (true, false) => None,
-            }
-            .unwrap_or(false)
+            let len = self.budget.samples(data, data.sampling.len(len));
+            data.cost
+                .time(len + 1, || {
+                    match (data.graph_type.is_3d_i(), data.graph_type.on_var()) {
+                        (true, true) => self.get_3d(
+                            data,
+                            self.var.x,
+                            self.var.x,
+                            self.var.y,
+                            self.var.y,
+                            len.isqrt(),
+                            len.isqrt(),
+                            b,
+                        ),
+                        (false, true) => self.get_2d(data, self.var.x, self.var.y, len, b),
+                        (false, false) => {
+                            let range = if data.graph_type.inv() {
+                                data.sampling.y
+                            } else {
+                                data.sampling.x
+                            };
+                            if let Some((start, end, len)) = Sampling::range(range, start, end, len)
+                            {
+                                #[cfg(feature = "kalc-lib")]
+                                if let Some(sequence) = &data.sequence {
+                                    return self.get_sequence(data, sequence, start, end, len, b);
+                                }
+                                #[cfg(feature = "kalc-lib")]
+                                if let Some(orbit) = &data.sampling.orbit {
+                                    return self.get_orbit(data, orbit, start, end, len, b);
+                                }
+                                #[cfg(feature = "kalc-lib")]
+                                if let Some(conformal) = &data.sampling.conformal {
+                                    return self.get_conformal(data, conformal, start, end, len, b);
+                                }
+                                #[cfg(feature = "kalc-lib")]
+                                if let Some(path) = &data.sampling.path {
+                                    return self.get_path(data, path, b);
+                                }
+                                #[cfg(feature = "kalc-lib")]
+                                if data.argand && !data.graph_type.inv() {
+                                    return self.get_argand(data, start, end, len, b);
+                                }
+                                self.get_2d(data, start, end, len, b)
+                            } else {
+                                *b = GraphData::None;
+                                Some(false)
+                            }
+                        }
+                        (true, false) => None,
+                    }
+                })
+                .unwrap_or(false)
         })
     }
     pub(crate) fn get_2d(
@@ -874,6 +1675,8 @@ impl Data {
         buffer: &mut GraphData,
     ) -> Option<bool> {
         let dx = (end - start) / len as f64;
+        #[cfg(feature = "kalc-lib")]
+        let options = self.sample_options(sample_bits(start, end, len));
         Some(match &data.graph_type.val {
             Val::Num(n) => {
                 if let Some(c) = n {
@@ -883,21 +1686,17 @@ impl Data {
                     let data = (0..=len)
                         .into_par_iter()
                         .map(|i| {
-                            let xv = start + i as f64 * dx;
+                            let xv = Coord::new(start, i as f64 * dx);
                             #[cfg(feature = "kalc-lib")]
-                            let x = NumStr::new(Number::from_f64(xv, &self.options));
-                            #[cfg(feature = "kalc-lib")]
-                            if let Ok(Num(n)) = do_math(
-                                place_var(data.func.clone(), "y", x.clone()),
-                                self.options,
-                                place_funcvar(data.funcvar.clone(), "y", x),
-                            ) {
-                                (n.number.real().to_f64(), Complex::Complex(xv, 0.0))
-                            } else {
-                                (f64::NAN, Complex::Complex(f64::NAN, f64::NAN))
+                            match data.eval("y", xv, options) {
+                                Value::Num(re, _) => (re, Complex::Complex(xv.get(), 0.0)),
+                                v => {
+                                    data.failed.note(&v);
+                                    (f64::NAN, Complex::Complex(f64::NAN, f64::NAN))
+                                }
                             }
                             #[cfg(not(feature = "kalc-lib"))]
-                            (f(xv).to_options().0.unwrap(), Complex::Real(xv))
+                            (f(xv.get()).to_options().0.unwrap(), Complex::Real(xv.get()))
                         })
                         .collect::<Vec<(f64, Complex)>>();
                     #[cfg(feature = "kalc-lib")]
@@ -910,21 +1709,15 @@ impl Data {
                     let data = (0..=len)
                         .into_par_iter()
                         .map(|i| {
-                            let x = start + i as f64 * dx;
-                            #[cfg(feature = "kalc-lib")]
-                            let x = NumStr::new(Number::from_f64(x, &self.options));
+                            let x = Coord::new(start, i as f64 * dx);
                             #[cfg(feature = "kalc-lib")]
-                            if let Ok(Num(n)) = do_math(
-                                place_var(data.func.clone(), "x", x.clone()),
-                                self.options,
-                                place_funcvar(data.funcvar.clone(), "x", x),
-                            ) {
-                                Complex::Complex(n.number.real().to_f64(), n.number.imag().to_f64())
-                            } else {
-                                Complex::Complex(f64::NAN, f64::NAN)
+                            {
+                                let v = data.eval("x", x, options);
+                                data.failed.note(&v);
+                                v.complex()
                             }
                             #[cfg(not(feature = "kalc-lib"))]
-                            f(x)
+                            f(x.get())
                         })
                         .collect::<Vec<Complex>>();
                     #[cfg(feature = "kalc-lib")]
@@ -944,23 +1737,12 @@ impl Data {
                     let data = (0..=len)
                         .into_par_iter()
                         .map(|i| {
-                            let x = start + i as f64 * dx;
-                            let x = NumStr::new(Number::from_f64(x, &self.options));
-                            if let Ok(Vector(n)) = do_math(
-                                place_var(data.func.clone(), "x", x.clone()),
-                                self.options,
-                                place_funcvar(data.funcvar.clone(), "x", x),
-                            ) {
+                            let x = Coord::new(start, i as f64 * dx);
+                            if let Value::Vector(n) = data.eval("x", x, options) {
                                 if n.len() != 2 {
                                     (f64::NAN, Complex::Complex(f64::NAN, f64::NAN))
                                 } else {
-                                    (
-                                        n[0].number.real().to_f64(),
-                                        Complex::Complex(
-                                            n[1].number.real().to_f64(),
-                                            n[1].number.imag().to_f64(),
-                                        ),
-                                    )
+                                    (n[0].0, Complex::Complex(n[1].0, n[1].1))
                                 }
                             } else {
                                 (f64::NAN, Complex::Complex(f64::NAN, f64::NAN))
@@ -977,24 +1759,12 @@ impl Data {
                 let data = (0..=len)
                     .into_par_iter()
                     .map(|i| {
-                        let x = start + i as f64 * dx;
-                        let x = NumStr::new(Number::from_f64(x, &self.options));
-                        if let Ok(Vector(n)) = do_math(
-                            place_var(data.func.clone(), "x", x.clone()),
-                            self.options,
-                            place_funcvar(data.funcvar.clone(), "x", x),
-                        ) {
+                        let x = Coord::new(start, i as f64 * dx);
+                        if let Value::Vector(n) = data.eval("x", x, options) {
                             if n.len() != 3 {
                                 (f64::NAN, f64::NAN, Complex::Complex(f64::NAN, f64::NAN))
                             } else {
-                                (
-                                    n[0].number.real().to_f64(),
-                                    n[1].number.real().to_f64(),
-                                    Complex::Complex(
-                                        n[2].number.real().to_f64(),
-                                        n[2].number.imag().to_f64(),
-                                    ),
-                                )
+                                (n[0].0, n[1].0, Complex::Complex(n[2].0, n[2].1))
                             }
                         } else {
                             (f64::NAN, f64::NAN, Complex::Complex(f64::NAN, f64::NAN))
@@ -1010,19 +1780,14 @@ impl Data {
                 if data.graph_type.inv() {
                     let mut ndata: Vec<Vec<(f64, Complex)>> = Vec::with_capacity(len + 1);
                     for i in 0..=len {
-                        let xv = start + i as f64 * dx;
-                        let x = NumStr::new(Number::from_f64(xv, &self.options));
-                        if let Ok(Vector(v)) = do_math(
-                            place_var(data.func.clone(), "y", x.clone()),
-                            self.options,
-                            place_funcvar(data.funcvar.clone(), "y", x),
-                        ) {
+                        let xv = Coord::new(start, i as f64 * dx);
+                        if let Value::Vector(v) = data.eval("y", xv, options) {
                             let mut v = v.into_iter();
                             ndata.extend(vec![Vec::new(); v.len().saturating_sub(ndata.len())]);
                             for data in ndata.iter_mut() {
                                 let n = v
                                     .next()
-                                    .map(|n| (n.number.real().to_f64(), Complex::Real(xv)))
+                                    .map(|(re, _)| (re, Complex::Real(xv.get())))
                                     .unwrap_or((f64::NAN, Complex::Real(f64::NAN)));
                                 data.push(n)
                             }
@@ -1033,27 +1798,14 @@ impl Data {
                 } else {
                     let mut ndata: Vec<Vec<(f64, Complex)>> = Vec::new();
                     for i in 0..=len {
-                        let xv = start + i as f64 * dx;
-                        let x = NumStr::new(Number::from_f64(xv, &self.options));
-                        if let Ok(Vector(v)) = do_math(
-                            place_var(data.func.clone(), "x", x.clone()),
-                            self.options,
-                            place_funcvar(data.funcvar.clone(), "x", x),
-                        ) {
+                        let xv = Coord::new(start, i as f64 * dx);
+                        if let Value::Vector(v) = data.eval("x", xv, options) {
                             let mut v = v.into_iter();
                             ndata.extend(vec![Vec::new(); v.len().saturating_sub(ndata.len())]);
                             for data in ndata.iter_mut() {
                                 let n = v
                                     .next()
-                                    .map(|n| {
-                                        (
-                                            xv,
-                                            Complex::Complex(
-                                                n.number.real().to_f64(),
-                                                n.number.imag().to_f64(),
-                                            ),
-                                        )
-                                    })
+                                    .map(|(re, im)| (xv.get(), Complex::Complex(re, im)))
                                     .unwrap_or((f64::NAN, Complex::Complex(f64::NAN, f64::NAN)));
                                 data.push(n)
                             }
@@ -1087,301 +1839,878 @@ impl Data {
     }
 }
 #[cfg(feature = "kalc-lib")]
-fn take_vars(
-    function: &mut String,
-    options: &mut Options,
-    vars: &mut Vec<Variable<I, F, C>>,
-) -> Vec<String> {
-    let mut s = function
-        .split('#')
-        .map(|a| a.to_string())
-        .collect::<Vec<String>>();
-    let mut split = s
-        .remove(0)
-        .split(';')
-        .map(|a| a.to_string())
-        .collect::<Vec<String>>();
-    *function = split.pop().unwrap();
-    for s in &split {
-        silent_commands(
-            options,
-            &s.chars()
-                .filter(|&c| !c.is_whitespace())
-                .collect::<Vec<char>>(),
-        );
-        if s.contains('=') {
-            let _ = set_commands_or_vars(
-                &mut Colors::default(),
-                options,
-                vars,
-                &s.chars().collect::<Vec<char>>(),
-            );
+pub(crate) fn join_function(name: &[Name], blacklist: &[usize]) -> String {
+    let mut i = 0;
+    let mut func = Vec::with_capacity(name.len());
+    for n in name {
+        #[cfg(feature = "bincode")]
+        if crate::session::Command::parse(&n.name).is_some() {
+            i += n.vars.len() + 1;
+            continue;
         }
+        let mut v: Vec<String> = Vec::with_capacity(n.vars.len());
+        for a in &n.vars {
+            if !a.is_empty() && !blacklist.contains(&i) {
+                v.push(a.clone())
+            }
+            i += 1;
+        }
+        if !n.name.is_empty() || !v.is_empty() {
+            func.push(if v.is_empty() {
+                n.name.clone()
+            } else {
+                format!("{};{}", v.join(";"), n.name)
+            })
+        }
+        i += 1;
     }
-    if !s.is_empty() {
-        *function = format!("{function}#{}", s.join("#"))
-    }
-    split
+    func.join("#").replace(";#", ";")
 }
-#[allow(clippy::type_complexity)]
 #[cfg(feature = "kalc-lib")]
-pub(crate) fn init(
-    function: &str,
-    options: &mut Options,
-    mut vars: Vec<Variable<I, F, C>>,
-) -> Result<(Vec<Option<Plot>>, Vec<(Vec<String>, String)>, HowGraphing), &'static str> {
-    let mut function = function.to_string();
-    let mut split = vec![take_vars(&mut function, options, &mut vars)];
-    let data = if function.contains(';') {
-        let mut data = Vec::new();
-        let mut first = true;
-        for mut function in function.split('#').map(|a| a.to_string()) {
-            if !first {
-                split.push(take_vars(&mut function, options, &mut vars));
-            }
-            first = false;
-            let x = function.starts_with("x=");
-            let y = function.starts_with("y=");
-            data.push(
-                if let Ok((func, funcvar, how, _, _)) = kalc_lib::parse::input_var(
-                    &format!("({})", if x || y { &function[2..] } else { &function }),
-                    &vars,
-                    &mut Vec::new(),
-                    &mut 0,
-                    *options,
-                    false,
-                    0,
-                    Vec::new(),
-                    false,
-                    &mut Vec::new(),
-                    None,
-                    None,
-                ) {
-                    (function, func, funcvar, how, x)
-                } else {
-                    (
-                        function.to_string(),
-                        Vec::new(),
-                        Vec::new(),
-                        Default::default(),
-                        x,
-                    )
-                },
-            );
-        }
-        data
-    } else {
-        function
-            .split('#')
-            .collect::<Vec<&str>>()
-            .into_par_iter()
-            .map(|function| {
-                let x = function.starts_with("x=");
-                let y = function.starts_with("y=");
-                match kalc_lib::parse::input_var(
-                    &format!("({})", if x || y { &function[2..] } else { &function }),
-                    &vars,
-                    &mut Vec::new(),
-                    &mut 0,
-                    *options,
-                    false,
-                    0,
-                    Vec::new(),
-                    false,
-                    &mut Vec::new(),
-                    None,
-                    None,
-                ) {
-                    Ok((func, funcvar, how, _, _)) => (function.to_string(), func, funcvar, how, x),
-                    Err(_) => (
-                        function.to_string(),
-                        Vec::new(),
-                        Vec::new(),
-                        Default::default(),
-                        x,
-                    ),
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Function {
+    pub(crate) name: String,
+    pub(crate) vars: Vec<String>,
+    pub(crate) func: Vec<NumStr<I, F, C>>,
+    #[allow(clippy::type_complexity)]
+    pub(crate) funcvar: Vec<(String, Vec<NumStr<I, F, C>>)>,
+    //compiled with the angle mode it was parsed under
+    pub(crate) compiled: Option<Compiled>,
+    pub(crate) how: HowGraphing,
+    pub(crate) graph_type: Option<Type>,
+    pub(crate) error: Option<FunctionError>,
+    pub(crate) reuse: Option<usize>,
+    pub(crate) sampling: Sampling,
+    pub(crate) sequence: Option<Sequence>,
+    #[cfg(feature = "rug")]
+    pub(crate) precise: Precise,
+    pub(crate) branches: Vec<Tokens>,
+}
+#[cfg(feature = "kalc-lib")]
+struct Vars {
+    fast: Vec<Variable<I, F, C>>,
+    //the same, shared with the rug forms that may be parsed from them later
+    #[cfg(feature = "rug")]
+    shared: Arc<Vec<Variable<I, F, C>>>,
+}
+#[cfg(feature = "kalc-lib")]
+macro_rules! input_var {
+    ($input:expr, $vars:expr, $options:expr) => {
+        kalc_lib::parse::input_var(
+            $input,
+            $vars,
+            &mut Vec::new(),
+            &mut 0,
+            $options,
+            false,
+            0,
+            Vec::new(),
+            false,
+            &mut Vec::new(),
+            None,
+            None,
+        )
+    };
+}
+//the rug form of a function, parsed the first time rug is asked for since most plots never are
+#[cfg(feature = "rug")]
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Precise {
+    input: String,
+    vars: Arc<Vec<Variable<I, F, C>>>,
+    options: Options,
+    tokens: OnceLock<Option<Tokens>>,
+}
+#[cfg(feature = "rug")]
+impl Precise {
+    pub(crate) fn get(&self) -> Option<&Tokens> {
+        self.tokens
+            .get_or_init(|| {
+                if self.input.is_empty() {
+                    return None;
                 }
+                let mut options = self.options;
+                let vars = rug_vars(&self.vars, &mut options);
+                let (func, funcvar, _, _, _) = input_var!(&self.input, &vars, options).ok()?;
+                Some(Tokens::Rug(func, funcvar))
             })
-            .collect::<Vec<(
-                String,
-                Vec<NumStr<I, F, C>>,
-                Vec<(String, Vec<NumStr<I, F, C>>)>,
-                HowGraphing,
-                bool,
-            )>>()
-    };
-    if data.is_empty() {
-        return Err("no data");
+            .as_ref()
     }
-    let mut how = data
-        .iter()
-        .find_map(|d| if d.3.graph { Some(d.3) } else { None })
-        .unwrap_or(data[0].3);
-    let (a, b): (Vec<Option<Plot>>, Vec<String>) = data
-        .into_par_iter()
-        .map(|(name, func, funcvar, how, b)| {
-            let x = NumStr::new(Number::new(options));
-            let (f, fv) = match (how.x, how.y) {
-                (true, true) => (
-                    place_var(place_var(func.clone(), "x", x.clone()), "y", x.clone()),
-                    place_funcvar(place_funcvar(funcvar.clone(), "x", x.clone()), "y", x),
-                ),
-                (true, false) => (
-                    place_var(func.clone(), "x", x.clone()),
-                    place_funcvar(funcvar.clone(), "x", x.clone()),
-                ),
-                (false, true) => (
-                    place_var(func.clone(), "y", x.clone()),
-                    place_funcvar(funcvar.clone(), "y", x.clone()),
-                ),
-                (false, false) => (func.clone(), funcvar.clone()),
-            };
-            let graph_type = match do_math(f, *options, fv) {
-                Ok(Num(c)) if !how.graph => Type {
-                    val: Val::Num(Some(compact_constant(*c))),
-                    how,
-                    inv: Some(!b),
-                },
-                Ok(Num(_)) => Type {
-                    val: Val::Num(None),
-                    how,
-                    inv: None,
-                },
-                Ok(Vector(_)) if is_list(&func, &funcvar) => Type {
-                    val: Val::List,
-                    how,
-                    inv: None,
-                },
-                Ok(Vector(v)) if v.len() == 2 && !how.graph => Type {
-                    val: Val::Vector(Some(rupl::types::Vec2::new(
-                        v[0].number.real().to_f64(),
-                        v[1].number.real().to_f64(),
-                    ))),
-                    how,
-                    inv: None,
-                },
-                Ok(Vector(v)) if v.len() == 2 => Type {
-                    val: Val::Vector(None),
-                    how,
-                    inv: None,
-                },
-                Ok(Vector(v)) if v.len() == 3 => Type {
-                    val: Val::Vector3D,
-                    how,
-                    inv: None,
-                },
-                Ok(Matrix(m))
-                    if !how.graph
-                        && !m.is_empty()
-                        && (m[0].len() == 2 || m[0].len() == 3)
-                        && m.iter().all(|a| a.len() == m[0].len()) =>
-                {
-                    Type {
-                        val: Val::Matrix(if m[0].len() == 2 {
-                            Mat::D2(
-                                m.iter()
-                                    .map(|v| {
-                                        rupl::types::Vec2::new(
-                                            v[0].number.real().to_f64(),
-                                            v[1].number.real().to_f64(),
-                                        )
-                                    })
-                                    .collect(),
-                            )
-                        } else {
-                            Mat::D3(
-                                m.iter()
-                                    .map(|v| {
-                                        rupl::types::Vec3::new(
-                                            v[0].number.real().to_f64(),
-                                            v[1].number.real().to_f64(),
-                                            v[2].number.real().to_f64(),
-                                        )
-                                    })
-                                    .collect(),
-                            )
-                        }),
-                        how,
-                        inv: None,
-                    }
-                }
-                Ok(_) | Err(_) => {
-                    return (None, name);
-                }
-            };
-            (
-                Some(Plot {
-                    func,
-                    funcvar,
-                    graph_type,
-                }),
-                name,
-            )
-        })
-        .unzip();
-    if a.iter().all(|data| {
-        let Some(data) = data else { return true };
-        matches!(data.graph_type.val, Val::Matrix(Mat::D3(_)))
-    }) {
-        how.x = true;
-        how.y = true;
-    };
-    if b.is_empty() {
-        return Err("no data2");
+}
+#[cfg(feature = "kalc-lib")]
+pub(crate) fn constant(s: &str, options: Options, vars: &[Variable<I, F, C>]) -> Option<f64> {
+    point(s, options, vars).map(|(re, _)| re)
+}
+//s as a point of the complex plane
+#[cfg(feature = "kalc-lib")]
+pub(crate) fn point(s: &str, options: Options, vars: &[Variable<I, F, C>]) -> Option<(f64, f64)> {
+    if let Ok(f) = s.parse() {
+        return Some((f, 0.0));
     }
-    let mut v = Vec::with_capacity(b.len());
-    for _ in split.len()..b.len() {
-        split.push(Vec::new());
+    let (func, funcvar, how, _, _) = input_var!(s, vars, options).ok()?;
+    if how.x || how.y {
+        return None;
     }
-    for (b, a) in b.iter().zip(split.into_iter()) {
-        v.push((a, b.to_string()));
+    match do_math(func, options, funcvar) {
+        Ok(Num(n)) => Some((n.number.real().to_f64(), n.number.imag().to_f64()))
+            .filter(|(re, im)| !re.is_nan() && !im.is_nan()),
+        _ => None,
     }
-    Ok((a, v, how))
 }
+//a<x<b, x>a and the like as the variable and its bounds
 #[cfg(feature = "kalc-lib")]
-fn compact_constant(c: Number<I, F, C>) -> Complex {
-    match (
-        c.real().is_zero() && c.real().is_finite(),
-        c.imag().is_zero() && c.imag().is_finite(),
-    ) {
-        (true, true) => Complex::Real(0.0),
-        (false, true) => Complex::Real(c.real().to_f64()),
-        (true, false) => Complex::Imag(c.imag().to_f64()),
-        (false, false) => Complex::Complex(c.real().to_f64(), c.imag().to_f64()),
+fn inequality(item: &str, constant: impl Fn(&str) -> Option<f64>) -> Option<(String, f64, f64)> {
+    let item = item.replace("<=", "<").replace(">=", ">");
+    let less = item.contains('<');
+    if less && item.contains('>') {
+        return None;
     }
+    let mut parts = item.split(['<', '>']).collect::<Vec<&str>>();
+    if !less {
+        parts.reverse()
+    }
+    let (var, a, b) = match parts[..] {
+        [a, var, b] => (var, constant(a)?, constant(b)?),
+        [var, b] if var == "x" || var == "y" => (var, f64::NEG_INFINITY, constant(b)?),
+        [a, var] => (var, constant(a)?, f64::INFINITY),
+        _ => return None,
+    };
+    Some((var.to_owned(), a, b))
 }
+//the settings in a trailing {..} of name and where they start, None if there is no such block
+//or it reads as part of the function, like a parametric vector, and the message and character
+//position of the first bad item if it reads as settings but one of them is unknown or invalid
 #[cfg(feature = "kalc-lib")]
-fn compact(mut graph: Vec<Complex>) -> (Vec<Complex>, bool) {
-    let complex = graph.iter().any(|a| {
-        if let Complex::Complex(_, i) = a {
-            i != &0.0 && i.is_finite()
-        } else {
-            unreachable!()
+#[allow(clippy::type_complexity)]
+fn sampling(
+    name: &str,
+    options: Options,
+    vars: &[Variable<I, F, C>],
+) -> Option<Result<(Sampling, usize), (String, usize)>> {
+    let body = name.trim_end().strip_suffix('}')?;
+    let open = body.rfind('{')?;
+    if name[..open].trim().is_empty() || body[open + 1..].contains('}') {
+        return None;
+    }
+    let constant = |s: &str| constant(s, options, vars);
+    let mut sampling = Sampling::default();
+    let mut path = None;
+    let mut settings = false;
+    let mut bad = None;
+    let mut start = open + 1;
+    for raw in body[open + 1..].split(',') {
+        let item = raw
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>();
+        //items that can only be meant as settings, so a typo is reported rather than read as a vector
+        let meant = item.contains(['<', '>', '='])
+            || item.starts_with("samples")
+            || (!item.is_empty() && item.chars().all(char::is_alphabetic));
+        let set = setting(&item, raw, &mut sampling, &mut path, constant).is_some();
+        settings |= set || meant;
+        if !set && bad.is_none() {
+            let skipped = raw.len() - raw.trim_start().len();
+            bad = Some((raw.trim(), name[..start + skipped].chars().count()))
         }
-    });
-    if !complex {
-        graph = graph
-            .into_iter()
-            .map(|a| Complex::Real(a.to_options().0.unwrap()))
-            .collect()
-    } else if graph.iter().all(|a| {
-        if let Complex::Complex(r, _) = a {
-            r == &0.0 || !r.is_finite()
-        } else {
-            unreachable!()
+        start += raw.len() + 1
+    }
+    match bad {
+        Some((item, position)) if settings => {
+            return Some(Err((format!("invalid sampling setting {item}"), position)));
         }
-    }) {
-        graph = graph
-            .into_iter()
-            .map(|a| Complex::Imag(a.to_options().1.unwrap()))
-            .collect()
+        Some(_) => return None,
+        None => {}
     }
-    (graph, complex)
+    if let Some(path) = path {
+        let Some(traced) = trace(path, sampling.len(PATH), options, vars) else {
+            let position = name[..name.find(path).unwrap_or(open)].chars().count();
+            return Some(Err((format!("invalid sampling path {path}"), position)));
+        };
+        sampling.path = Some(traced)
+    }
+    Some(Ok((sampling, open)))
 }
+//reads one item of a {..} block into sampling, None if it is not a valid setting
 #[cfg(feature = "kalc-lib")]
-fn compact_coord(mut graph: Vec<(f64, Complex)>) -> (Vec<(f64, Complex)>, bool) {
-    let complex = graph.iter().any(|(_, a)| {
-        if let Complex::Complex(_, i) = a {
+fn setting<'a>(
+    item: &str,
+    raw: &'a str,
+    sampling: &mut Sampling,
+    path: &mut Option<&'a str>,
+    constant: impl Fn(&str) -> Option<f64>,
+) -> Option<()> {
+    if ["segment=", "circle=", "path="]
+        .iter()
+        .any(|p| item.starts_with(p))
+    {
+        *path = Some(raw.trim())
+    } else if item == "stems" {
+        sampling.stems = true
+    } else if item == "argand" {
+        sampling.argand = true
+    } else if item == "precise" {
+        sampling.precise = true
+    } else if let Some(limit) = item.strip_prefix("limit=") {
+        let (min, max) = limit.split_once("..").unwrap_or((limit, "1"));
+        let (min, max) = (constant(min)?, constant(max)?);
+        (0.0 < min && min <= max && max <= 1.0).then_some(())?;
+        sampling.limit = Some(Limit { min, max })
+    } else if item == "cobweb" {
+        sampling.orbit = Some(Orbit::Cobweb(None))
+    } else if let Some(x0) = item.strip_prefix("cobweb=") {
+        sampling.orbit = Some(Orbit::Cobweb(Some(constant(x0)?)))
+    } else if let Some(r) = item.strip_prefix("bifurcation=") {
+        (!r.is_empty() && r.chars().all(char::is_alphabetic)).then_some(())?;
+        sampling.orbit = Some(Orbit::Bifurcation(r.to_owned()))
+    } else if item == "fractal" {
+        sampling.fractal.get_or_insert_default();
+    } else if let Some(n) = item.strip_prefix("fractal=") {
+        sampling.fractal.get_or_insert_default().iterations = n.parse().ok().filter(|n| *n > 0)?
+    } else if let Some(r) = item.strip_prefix("bailout=") {
+        sampling.fractal.get_or_insert_default().bailout = constant(r).filter(|r| *r > 1.0)?
+    } else if item == "grid" {
+        sampling.conformal.get_or_insert_default();
+    } else if let Some(n) = item.strip_prefix("grid=") {
+        sampling.conformal.get_or_insert_default().lines = n.parse().ok().filter(|n| *n > 0)?
+    } else if item == "branches" {
+        sampling.branches = Some(2)
+    } else if let Some(n) = item.strip_prefix("branches=") {
+        sampling.branches = Some(n.parse().ok().filter(|n| *n >= 1)?)
+    } else if item == "sphere" {
+        sampling.sphere = Some(32)
+    } else if let Some(n) = item.strip_prefix("sphere=") {
+        sampling.sphere = Some(n.parse().ok().filter(|n| *n >= 2)?)
+    } else if item == "polar" {
+        sampling.conformal.get_or_insert_default().polar = true
+    } else if let Some(steps) = item.strip_prefix("steps=") {
+        sampling.steps = Some(steps.parse().ok().filter(|s| *s > 0)?)
+    } else if let Some(mult) = item.strip_prefix("samples*") {
+        sampling.mult = Some(constant(mult).filter(|m| *m > 0.0)?);
+    } else if let Some((var, range)) = item.split_once('=')
+        && let Some((a, b)) = range.split_once("..")
+    {
+        *sampling.axis(var)? = Some(Range::Fixed(constant(a)?, constant(b)?));
+    } else {
+        let (var, a, b) = inequality(item, constant)?;
+        let axis = sampling.axis(&var)?;
+        *axis = Some(match *axis {
+            Some(Range::Within(c, d)) => Range::Within(a.max(c), b.min(d)),
+            _ => Range::Within(a, b),
+        });
+    }
+    Some(())
+}
+#[cfg(feature = "kalc-lib")]
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct FunctionError {
+    pub(crate) function: String,
+    pub(crate) message: String,
+    pub(crate) position: Option<usize>,
+}
+#[cfg(feature = "kalc-lib")]
+impl std::fmt::Display for FunctionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.function, self.message)?;
+        if let Some(position) = self.position {
+            write!(f, " at {}", position + 1)?;
+        }
+        Ok(())
+    }
+}
+#[cfg(feature = "kalc-lib")]
+impl std::error::Error for FunctionError {}
+#[cfg(feature = "kalc-lib")]
+impl FunctionError {
+    //the function with the character at the error underlined, for text that cannot be coloured
+    pub(crate) fn marked(&self) -> String {
+        self.function
+            .chars()
+            .enumerate()
+            .flat_map(|(i, c)| [Some(c), (Some(i) == self.position).then_some('\u{332}')])
+            .flatten()
+            .collect()
+    }
+}
+#[cfg(feature = "kalc-lib")]
+fn unbalanced(function: &str) -> Option<usize> {
+    let mut open = Vec::new();
+    for (i, c) in function.chars().enumerate() {
+        match c {
+            '(' | '{' | '[' => open.push((i, c)),
+            ')' | '}' | ']' => match open.pop() {
+                Some((_, o)) if matches!((o, c), ('(', ')') | ('{', '}') | ('[', ']')) => {}
+                _ => return Some(i),
+            },
+            _ => {}
+        }
+    }
+    open.first().map(|(i, _)| *i)
+}
+#[cfg(feature = "kalc-lib")]
+impl Function {
+    fn parse(mut self, options: Options, vars: &Vars) -> Self {
+        if self.reuse.is_some() {
+            return self;
+        }
+        let name = match sampling(&self.name, options, &vars.fast) {
+            Some(Ok((sampling, i))) => {
+                self.sampling = sampling;
+                &self.name[..i]
+            }
+            Some(Err((message, position))) => {
+                self.fail(message, Some(position));
+                return self;
+            }
+            None => self.name.as_str(),
+        };
+        if let Some(recurrence) = Recurrence::parse(name, options, &vars.fast) {
+            self.how = HowGraphing {
+                graph: true,
+                x: true,
+                ..HowGraphing::default()
+            };
+            self.sequence = Some(Sequence::Recurrence(recurrence));
+            return self;
+        }
+        let x = name.starts_with("x=");
+        let y = name.starts_with("y=");
+        let how = if matches!(self.sampling.orbit, Some(Orbit::Bifurcation(_)))
+            || self.sampling.conformal.is_some()
+            || self.sampling.path.is_some()
+        {
+            Some(HowGraphing {
+                graph: true,
+                x: true,
+                ..HowGraphing::default()
+            })
+        } else if self.sampling.fractal.is_some() {
+            Some(HowGraphing {
+                graph: true,
+                x: true,
+                y: true,
+                ..HowGraphing::default()
+            })
+        } else {
+            None
+        };
+        let input = if let Some(Orbit::Bifurcation(r)) = &self.sampling.orbit {
+            format!("({})", substitute(name, r, "y"))
+        } else if let Some(fractal) = &mut self.sampling.fractal {
+            fractal.julia = !mentions(name, "c");
+            format!("({})", substitute(&substitute(name, "z", "x"), "c", "y"))
+        } else if !x && !y && explicit(name) {
+            self.sequence = Some(Sequence::Explicit);
+            format!("({})", substitute(name, "n", "x"))
+        } else {
+            format!("({})", if x || y { &name[2..] } else { name })
+        };
+        match input_var!(&input, &vars.fast, options) {
+            Ok((func, funcvar, parsed, _, _)) => {
+                self.compiled = compile(&func, &funcvar, &options);
+                self.func = func;
+                self.funcvar = funcvar;
+                self.how = how.unwrap_or(parsed);
+            }
+            Err(e) => self.fail(e.to_string(), unbalanced(&self.name)),
+        }
+        #[cfg(feature = "rug")]
+        if self.error.is_none() {
+            self.precise = Precise {
+                input: input.clone(),
+                vars: vars.shared.clone(),
+                options,
+                tokens: OnceLock::new(),
+            }
+        }
+        if let Some(n) = self.sampling.branches
+            && self.error.is_none()
+        {
+            for k in 1..n {
+                match input_var!(&format!("({})", sheet(name, k)), &vars.fast, options) {
+                    Ok((func, funcvar, _, _, _)) => self.branches.push(
+                        compile(&func, &funcvar, &options)
+                            .map_or(Tokens::F64(func, funcvar), Tokens::Compiled),
+                    ),
+                    Err(e) => {
+                        self.fail(e.to_string(), None);
+                        break;
+                    }
+                }
+            }
+        }
+        self
+    }
+    fn fail(&mut self, message: String, position: Option<usize>) {
+        if self.error.is_none() && !self.name.trim().is_empty() {
+            self.error = Some(FunctionError {
+                function: self.name.clone(),
+                message,
+                position,
+            })
+        }
+    }
+    fn classify(mut self, options: Options) -> Self {
+        if self.reuse.is_some() {
+            return self;
+        }
+        if self.sampling.fractal.is_some() && compile(&self.func, &self.funcvar, &options).is_none()
+        {
+            self.fail(
+                "a fractal can only use arithmetic and elementary functions of z and c".to_owned(),
+                None,
+            );
+            return self;
+        }
+        if matches!(self.sequence, Some(Sequence::Recurrence(_)))
+            || matches!(self.sampling.orbit, Some(Orbit::Bifurcation(_)))
+            || self.sampling.conformal.is_some()
+            || self.sampling.path.is_some()
+        {
+            self.graph_type = Some(Type {
+                val: Val::Num(None),
+                how: self.how,
+                inv: None,
+            });
+            return self;
+        }
+        let how = self.how;
+        let x = NumStr::new(Number::new(options));
+        let (f, fv) = match (how.x, how.y) {
+            (true, true) => (
+                place_var(place_var(self.func.clone(), "x", x.clone()), "y", x.clone()),
+                place_funcvar(place_funcvar(self.funcvar.clone(), "x", x.clone()), "y", x),
+            ),
+            (true, false) => (
+                place_var(self.func.clone(), "x", x.clone()),
+                place_funcvar(self.funcvar.clone(), "x", x.clone()),
+            ),
+            (false, true) => (
+                place_var(self.func.clone(), "y", x.clone()),
+                place_funcvar(self.funcvar.clone(), "y", x.clone()),
+            ),
+            (false, false) => (self.func.clone(), self.funcvar.clone()),
+        };
+        self.graph_type = match do_math(f, options, fv) {
+            Ok(Num(c)) if !how.graph => Some(Type {
+                val: Val::Num(Some(compact_constant(*c))),
+                how,
+                inv: Some(!self.name.starts_with("x=")),
+            }),
+            Ok(Num(_)) => Some(Type {
+                val: Val::Num(None),
+                how,
+                inv: None,
+            }),
+            Ok(Vector(_)) if is_list(&self.func, &self.funcvar) => Some(Type {
+                val: Val::List,
+                how,
+                inv: None,
+            }),
+            Ok(Vector(v)) if v.len() == 2 && !how.graph => Some(Type {
+                val: Val::Vector(Some(rupl::types::Vec2::new(
+                    v[0].number.real().to_f64(),
+                    v[1].number.real().to_f64(),
+                ))),
+                how,
+                inv: None,
+            }),
+            Ok(Vector(v)) if v.len() == 2 => Some(Type {
+                val: Val::Vector(None),
+                how,
+                inv: None,
+            }),
+            Ok(Vector(v)) if v.len() == 3 => Some(Type {
+                val: Val::Vector3D,
+                how,
+                inv: None,
+            }),
+            Ok(Matrix(m))
+                if !how.graph
+                    && !m.is_empty()
+                    && (m[0].len() == 2 || m[0].len() == 3)
+                    && m.iter().all(|a| a.len() == m[0].len()) =>
+            {
+                Some(Type {
+                    val: Val::Matrix(if m[0].len() == 2 {
+                        Mat::D2(
+                            m.iter()
+                                .map(|v| {
+                                    rupl::types::Vec2::new(
+                                        v[0].number.real().to_f64(),
+                                        v[1].number.real().to_f64(),
+                                    )
+                                })
+                                .collect(),
+                        )
+                    } else {
+                        Mat::D3(
+                            m.iter()
+                                .map(|v| {
+                                    rupl::types::Vec3::new(
+                                        v[0].number.real().to_f64(),
+                                        v[1].number.real().to_f64(),
+                                        v[2].number.real().to_f64(),
+                                    )
+                                })
+                                .collect(),
+                        )
+                    }),
+                    how,
+                    inv: None,
+                })
+            }
+            Ok(_) => {
+                self.fail("result can not be graphed".to_owned(), None);
+                None
+            }
+            Err(e) => {
+                self.fail(e.to_string(), None);
+                None
+            }
+        };
+        self
+    }
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn into_parts(self) -> (Option<Plot>, (Vec<String>, String), Option<FunctionError>) {
+        let Function {
+            name,
+            vars,
+            func,
+            funcvar,
+            compiled,
+            graph_type,
+            error,
+            sampling,
+            sequence,
+            #[cfg(feature = "rug")]
+            precise,
+            branches,
+            ..
+        } = self;
+        (
+            graph_type.map(|graph_type| Plot {
+                compiled,
+                func,
+                funcvar,
+                graph_type,
+                #[cfg(feature = "rug")]
+                precise,
+                cost: Cost::default(),
+                limit: sampling.limit.unwrap_or_default(),
+                argand: sampling.argand,
+                sampling,
+                sequence,
+                winding: Winding::default(),
+                singularities: Found::default(),
+                branches,
+                failed: Failed::default(),
+            }),
+            (vars, name),
+            error,
+        )
+    }
+}
+//s split at each c outside of brackets
+#[cfg(feature = "kalc-lib")]
+fn split_outside(s: &str, c: char) -> Vec<&str> {
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut parts = Vec::new();
+    for (i, ch) in s.char_indices() {
+        match ch {
+            '(' | '{' | '[' => depth += 1,
+            ')' | '}' | ']' => depth = depth.saturating_sub(1),
+            _ if ch == c && depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + c.len_utf8()
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+#[cfg(feature = "kalc-lib")]
+fn split_function(function: &str) -> Vec<Function> {
+    split_outside(function, '#')
+        .into_iter()
+        .map(|segment| {
+            let mut vars = split_outside(segment, ';')
+                .into_iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<String>>();
+            let name = vars.pop().unwrap();
+            Function {
+                name,
+                vars,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+#[cfg(feature = "kalc-lib")]
+fn take_vars(prelude: &[String], options: &mut Options, vars: &mut Vars) {
+    for s in prelude {
+        silent_commands(
+            options,
+            &s.chars()
+                .filter(|&c| !c.is_whitespace())
+                .collect::<Vec<char>>(),
+        );
+        if s.contains('=') {
+            let s = s.chars().collect::<Vec<char>>();
+            let _ = set_commands_or_vars(&mut Colors::default(), options, &mut vars.fast, &s);
+            #[cfg(feature = "rug")]
+            {
+                vars.shared = Arc::new(vars.fast.clone())
+            }
+        }
+    }
+}
+//the defaults at rug precision with every variable defined or redefined in vars, such as those
+//kalc passes with -d, parsed again from its text
+#[cfg(feature = "rug")]
+fn rug_vars(vars: &[Variable<I, F, C>], options: &mut Options) -> Vec<Variable<RI, RF, RC>> {
+    let mut rug = get_vars(*options);
+    for var in vars {
+        if !rug
+            .iter()
+            .any(|r| r.name == var.name && r.unparsed == var.unparsed)
+        {
+            let s = var
+                .name
+                .iter()
+                .copied()
+                .chain(std::iter::once('='))
+                .chain(var.unparsed.chars())
+                .collect::<Vec<char>>();
+            let _ = set_commands_or_vars(&mut Colors::default(), options, &mut rug, &s);
+        }
+    }
+    rug
+}
+#[cfg(feature = "kalc-lib")]
+fn definition(var: &str) -> Option<(&str, &str)> {
+    let (name, value) = var.split_once('=')?;
+    let name = name.split('(').next().unwrap_or(name).trim();
+    (!name.is_empty()).then_some((name, value))
+}
+//for each new function the index of an identical old one whose result is still valid,
+//None where the function itself changed or it mentions a prelude variable whose definition changed,
+//matched as whole identifiers so a does not catch tan or gamma
+#[cfg(feature = "kalc-lib")]
+#[allow(clippy::type_complexity)]
+fn reusable(old: &[(Vec<String>, String)], new: &[(Vec<String>, String)]) -> Vec<Option<usize>> {
+    let commands = |list: &[(Vec<String>, String)]| {
+        list.iter()
+            .flat_map(|(vars, _)| vars)
+            .filter(|v| !v.contains('='))
+            .collect::<Vec<&String>>()
+    };
+    if commands(old) != commands(new) {
+        return vec![None; new.len()];
+    }
+    let definitions = |list: &[(Vec<String>, String)]| {
+        list.iter()
+            .flat_map(|(vars, _)| vars)
+            .filter_map(|v| definition(v))
+            .map(|(name, value)| (name.to_owned(), value.to_owned()))
+            .collect::<Vec<(String, String)>>()
+    };
+    let (a, b) = (definitions(old), definitions(new));
+    let mut changed = a
+        .iter()
+        .filter(|d| !b.contains(d))
+        .chain(b.iter().filter(|d| !a.contains(d)))
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<String>>();
+    loop {
+        let more = a
+            .iter()
+            .chain(b.iter())
+            .filter(|(name, value)| {
+                !changed.contains(name) && changed.iter().any(|c| mentions(value, c))
+            })
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<String>>();
+        if more.is_empty() {
+            break;
+        }
+        changed.extend(more);
+    }
+    let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            }
+        }
+    }
+    let mut reuse = vec![None; new.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            reuse[j] = Some(i);
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            i += 1
+        } else {
+            j += 1
+        }
+    }
+    reuse
+        .into_iter()
+        .zip(new)
+        .map(|(reuse, (vars, name))| {
+            reuse.filter(|_| {
+                !changed
+                    .iter()
+                    .any(|c| mentions(name, c) || vars.iter().any(|v| mentions(v, c)))
+            })
+        })
+        .collect()
+}
+#[cfg(feature = "kalc-lib")]
+fn restructure(plot: &mut Graph, changes: Vec<Option<usize>>) -> Vec<Option<usize>> {
+    let mut old = plot
+        .take_data()
+        .into_iter()
+        .map(Some)
+        .collect::<Vec<Option<GraphData>>>();
+    let mut dirty = Vec::new();
+    let data = changes
+        .into_iter()
+        .enumerate()
+        .map(|(i, reuse)| {
+            reuse
+                .and_then(|j| old.get_mut(j)?.take())
+                .unwrap_or_else(|| {
+                    dirty.push(Some(i));
+                    GraphData::None
+                })
+        })
+        .collect();
+    plot.set_data(data);
+    dirty
+}
+#[cfg(feature = "kalc-lib")]
+pub(crate) fn init(
+    function: &str,
+    options: &mut Options,
+    vars: Vec<Variable<I, F, C>>,
+) -> (Vec<Function>, HowGraphing) {
+    init_from(function, options, vars, &[])
+}
+//like init, but functions matching an unchanged entry of old are only marked for reuse
+#[cfg(feature = "kalc-lib")]
+#[allow(clippy::type_complexity)]
+pub(crate) fn init_from(
+    function: &str,
+    options: &mut Options,
+    vars: Vec<Variable<I, F, C>>,
+    old: &[(Vec<String>, String)],
+) -> (Vec<Function>, HowGraphing) {
+    let mut vars = Vars {
+        #[cfg(feature = "rug")]
+        shared: Arc::new(vars.clone()),
+        fast: vars,
+    };
+    let mut functions = split_function(function);
+    let sources = functions
+        .iter()
+        .map(|f| (f.vars.clone(), f.name.clone()))
+        .collect::<Vec<(Vec<String>, String)>>();
+    for (f, reuse) in functions.iter_mut().zip(reusable(old, &sources)) {
+        f.reuse = reuse
+    }
+    let functions = if functions.iter().skip(1).any(|f| !f.vars.is_empty()) {
+        functions
+            .into_iter()
+            .map(|f| {
+                take_vars(&f.vars, options, &mut vars);
+                f.parse(*options, &vars)
+            })
+            .collect::<Vec<Function>>()
+    } else {
+        take_vars(&functions[0].vars, options, &mut vars);
+        let options = *options;
+        functions
+            .into_par_iter()
+            .map(|f| f.parse(options, &vars))
+            .collect::<Vec<Function>>()
+    };
+    let mut how = functions
+        .iter()
+        .find_map(|f| if f.how.graph { Some(f.how) } else { None })
+        .unwrap_or(functions[0].how);
+    let options = *options;
+    let functions = functions
+        .into_par_iter()
+        .map(|f| f.classify(options))
+        .collect::<Vec<Function>>();
+    if functions.iter().filter(|f| f.reuse.is_none()).all(|f| {
+        let Some(graph_type) = &f.graph_type else {
+            return true;
+        };
+        matches!(graph_type.val, Val::Matrix(Mat::D3(_)))
+    }) {
+        how.x = true;
+        how.y = true;
+    };
+    (functions, how)
+}
+#[cfg(feature = "kalc-lib")]
+fn compact_constant(c: Number<I, F, C>) -> Complex {
+    match (
+        c.real().is_zero() && c.real().is_finite(),
+        c.imag().is_zero() && c.imag().is_finite(),
+    ) {
+        (true, true) => Complex::Real(0.0),
+        (false, true) => Complex::Real(c.real().to_f64()),
+        (true, false) => Complex::Imag(c.imag().to_f64()),
+        (false, false) => Complex::Complex(c.real().to_f64(), c.imag().to_f64()),
+    }
+}
+#[cfg(feature = "kalc-lib")]
+pub(crate) fn domain(graphtype: GraphType) -> bool {
+    matches!(graphtype, GraphType::Domain | GraphType::DomainAlt)
+}
+#[cfg(feature = "kalc-lib")]
+pub(crate) fn compact(mut graph: Vec<Complex>) -> (Vec<Complex>, bool) {
+    let complex = graph.iter().any(|a| {
+        if let Complex::Complex(_, i) = a {
+            i != &0.0 && i.is_finite()
+        } else {
+            unreachable!()
+        }
+    });
+    if !complex {
+        graph = graph
+            .into_iter()
+            .map(|a| Complex::Real(a.to_options().0.unwrap()))
+            .collect()
+    } else if graph.iter().all(|a| {
+        if let Complex::Complex(r, _) = a {
+            r == &0.0 || !r.is_finite()
+        } else {
+            unreachable!()
+        }
+    }) {
+        graph = graph
+            .into_iter()
+            .map(|a| Complex::Imag(a.to_options().1.unwrap()))
+            .collect()
+    }
+    (graph, complex)
+}
+#[cfg(feature = "kalc-lib")]
+fn compact_coord(mut graph: Vec<(f64, Complex)>) -> (Vec<(f64, Complex)>, bool) {
+    let complex = graph.iter().any(|(_, a)| {
+        if let Complex::Complex(_, i) = a {
             i != &0.0 && i.is_finite()
         } else {
             unreachable!()
@@ -1502,3 +2831,935 @@ fn f(x: f64) -> Complex {
 fn is_complex() -> bool {
     true
 }
+#[cfg(test)]
+#[cfg(feature = "kalc-lib")]
+mod tests {
+    use super::*;
+    use kalc_lib::load_vars::get_vars;
+    use rupl::types::Show;
+    fn parse(function: &str) -> (Vec<Function>, HowGraphing) {
+        let mut options = Options {
+            prec: 128,
+            graph_prec: 128,
+            graphing: true,
+            ..Options::default()
+        };
+        let vars = get_vars(options);
+        init(function, &mut options, vars)
+    }
+    fn split(function: &str) -> Vec<(Vec<String>, String)> {
+        split_function(function)
+            .into_iter()
+            .map(|f| (f.vars, f.name))
+            .collect()
+    }
+    fn seg(vars: &[&str], name: &str) -> (Vec<String>, String) {
+        (
+            vars.iter().map(|v| v.to_string()).collect(),
+            name.to_owned(),
+        )
+    }
+    fn names(function: &str) -> Vec<Name> {
+        split_function(function)
+            .into_iter()
+            .map(|f| Name {
+                name: f.name,
+                show: Show::None,
+                vars: f.vars,
+            })
+            .collect()
+    }
+    fn val(f: &Function) -> &Val {
+        &f.graph_type.as_ref().unwrap().val
+    }
+    #[test]
+    fn split_single() {
+        assert_eq!(split("sin(x)"), [seg(&[], "sin(x)")]);
+    }
+    #[test]
+    fn split_prelude() {
+        assert_eq!(split("a=2;b=3;a x+b"), [seg(&["a=2", "b=3"], "a x+b")]);
+    }
+    #[test]
+    fn split_many() {
+        assert_eq!(
+            split("sin(x)#cos(x)"),
+            [seg(&[], "sin(x)"), seg(&[], "cos(x)")]
+        );
+    }
+    #[test]
+    fn split_prelude_per_function() {
+        assert_eq!(
+            split("a=1;x#b=2;y"),
+            [seg(&["a=1"], "x"), seg(&["b=2"], "y")]
+        );
+    }
+    #[test]
+    fn split_empty_segments() {
+        assert_eq!(
+            split("sin(x)##cos(x)#"),
+            [
+                seg(&[], "sin(x)"),
+                seg(&[], ""),
+                seg(&[], "cos(x)"),
+                seg(&[], "")
+            ]
+        );
+        assert_eq!(split(""), [seg(&[], "")]);
+        assert_eq!(split("#"), [seg(&[], ""), seg(&[], "")]);
+    }
+    #[test]
+    fn split_trailing_semicolon() {
+        assert_eq!(split("a=2;"), [seg(&["a=2"], "")]);
+        assert_eq!(split(";x"), [seg(&[""], "x")]);
+        assert_eq!(split("a=2;;x"), [seg(&["a=2", ""], "x")]);
+    }
+    #[test]
+    fn split_hash_inside_prelude() {
+        assert_eq!(split("a=1#b=2;x"), [seg(&[], "a=1"), seg(&["b=2"], "x")]);
+        assert_eq!(split("a={1#2};x"), [seg(&["a={1#2}"], "x")]);
+        assert_eq!(split("f(x;y)#z"), [seg(&[], "f(x;y)"), seg(&[], "z")]);
+        assert_eq!(split("a=(1;x"), [seg(&[], "a=(1;x")]);
+    }
+    #[test]
+    fn join_roundtrip() {
+        for function in [
+            "sin(x)",
+            "a=2;a x",
+            "x#cos(x)",
+            "a=1;x#b=2;y",
+            "a=1;b=2;x#y",
+            "x=y^2#y=x",
+        ] {
+            assert_eq!(join_function(&names(function), &[]), function);
+        }
+    }
+    #[test]
+    fn join_drops_empty_segments() {
+        assert_eq!(join_function(&names("x##y#"), &[]), "x#y");
+        assert_eq!(join_function(&names("a=1;;x"), &[]), "a=1;x");
+    }
+    #[test]
+    fn join_moves_dangling_prelude_forward() {
+        assert_eq!(join_function(&names("a=1;#x"), &[]), "a=1;x");
+        assert_eq!(join_function(&names("x#a=1;"), &[]), "x#a=1;");
+    }
+    #[test]
+    fn join_skips_blacklisted_vars() {
+        assert_eq!(join_function(&names("a=1;b=2;x"), &[0]), "b=2;x");
+        assert_eq!(join_function(&names("a=1;x#b=2;y"), &[2]), "a=1;x#y");
+    }
+    #[test]
+    fn init_function() {
+        let (functions, how) = parse("sin(x)");
+        assert_eq!(functions.len(), 1);
+        assert_eq!(functions[0].name, "sin(x)");
+        assert!(functions[0].error.is_none());
+        assert!(matches!(val(&functions[0]), Val::Num(None)));
+        assert!(how.graph && how.x && !how.y);
+    }
+    #[test]
+    fn init_constant() {
+        let (functions, _) = parse("2");
+        assert!(matches!(val(&functions[0]), Val::Num(Some(Complex::Real(r))) if *r == 2.0));
+    }
+    #[test]
+    fn init_inverse() {
+        let (functions, _) = parse("x=y^2#y=x^2");
+        assert_eq!(functions[0].name, "x=y^2");
+        assert!(functions[0].graph_type.as_ref().unwrap().inv());
+        assert!(!functions[1].graph_type.as_ref().unwrap().inv());
+        let (functions, _) = parse("x=2#2");
+        assert!(!functions[0].graph_type.as_ref().unwrap().inv());
+        assert!(functions[1].graph_type.as_ref().unwrap().inv());
+    }
+    #[test]
+    fn init_surface() {
+        let (functions, how) = parse("sin(x)cos(y)");
+        assert!(functions[0].graph_type.as_ref().unwrap().is_3d_o());
+        assert!(how.x && how.y);
+    }
+    #[test]
+    fn init_kinds() {
+        let (functions, _) = parse("{1,2}#{cos(x),sin(x)}#{cos(x),sin(x),x}#±sqrt(x)");
+        assert!(matches!(val(&functions[0]), Val::Vector(Some(_))));
+        assert!(matches!(val(&functions[1]), Val::Vector(None)));
+        assert!(matches!(val(&functions[2]), Val::Vector3D));
+        assert!(matches!(val(&functions[3]), Val::List));
+    }
+    #[test]
+    fn init_matrix() {
+        let (functions, how) = parse("{{0,0},{1,2}}");
+        assert!(matches!(val(&functions[0]), Val::Matrix(Mat::D2(m)) if m.len() == 2));
+        assert!(!how.y);
+        let (functions, how) = parse("{{0,0,0},{1,1,1}}");
+        assert!(matches!(val(&functions[0]), Val::Matrix(Mat::D3(_))));
+        assert!(how.x && how.y);
+    }
+    #[test]
+    fn init_how_from_first_graph() {
+        let (functions, how) = parse("2#x^2");
+        assert!(!functions[0].how.graph);
+        assert!(how.graph && how.x);
+    }
+    #[test]
+    fn init_parse_error() {
+        let (functions, _) = parse("*#x");
+        assert!(functions[0].graph_type.is_none());
+        assert!(functions[0].error.is_some());
+        assert!(functions[1].graph_type.is_some());
+        assert!(functions[1].error.is_none());
+    }
+    #[test]
+    fn init_empty_segments() {
+        let (functions, _) = parse("sin(x)##cos(x)");
+        assert_eq!(functions.len(), 3);
+        assert_eq!(functions[1].name, "");
+        assert!(functions[1].graph_type.is_none());
+        assert!(functions[1].error.is_none());
+        assert!(functions[0].graph_type.is_some() && functions[2].graph_type.is_some());
+        let (functions, _) = parse("");
+        assert_eq!(functions.len(), 1);
+        assert!(functions[0].graph_type.is_none());
+    }
+    #[test]
+    fn init_unbalanced_position() {
+        let (functions, _) = parse("sin(x))+1#{1,(2}");
+        let error = functions[0].error.as_ref().unwrap();
+        assert_eq!(error.function, "sin(x))+1");
+        assert_eq!(error.position, Some(6));
+        assert_eq!(functions[1].error.as_ref().unwrap().position, Some(5));
+    }
+    #[test]
+    fn unbalanced_brackets() {
+        assert_eq!(unbalanced("sin(x)"), None);
+        assert_eq!(unbalanced("{(x),[y]}"), None);
+        assert_eq!(unbalanced("x)"), Some(1));
+        assert_eq!(unbalanced("(x]"), Some(2));
+        assert_eq!(unbalanced("((x)"), Some(0));
+        assert_eq!(unbalanced("é(x"), Some(1));
+    }
+    #[test]
+    fn error_display() {
+        let error = FunctionError {
+            function: "x)".to_owned(),
+            message: "bad input".to_owned(),
+            position: Some(1),
+        };
+        assert_eq!(error.to_string(), "x): bad input at 2");
+        assert_eq!(error.marked(), "x)\u{332}");
+    }
+    #[test]
+    fn sample_bits_deep() {
+        assert_eq!(deep(sample_bits(-8.0, 8.0, 512)), None);
+        assert_eq!(deep(sample_bits(1.0, 1.0, 512)), None);
+        assert_eq!(deep(sample_bits(1.0, 1.0 + 1e-9, 512)), None);
+        let bits = deep(sample_bits(1.0, 1.0 + 1e-14, 512)).unwrap();
+        assert!(bits > f64::MANTISSA_DIGITS && bits < 128);
+        assert!(!floor(1.0, 1.0 + 1e-12));
+        assert!(floor(1.0, 1.0 + 1e-14));
+        assert!(floor(-1.0, -1.0 + 4.0 * f64::EPSILON));
+    }
+    #[test]
+    fn coord_offset() {
+        let c = Coord::new(1.0, 1e-17);
+        assert_eq!(c.get(), 1.0);
+        assert_eq!((c.start, c.offset), (1.0, 1e-17));
+    }
+    #[test]
+    fn init_trailing_semicolon() {
+        let (functions, _) = parse("a=2;");
+        assert_eq!(functions.len(), 1);
+        assert_eq!(functions[0].vars, ["a=2"]);
+        assert_eq!(functions[0].name, "");
+        assert!(functions[0].graph_type.is_none());
+        assert!(functions[0].error.is_none());
+    }
+    #[test]
+    fn init_prelude() {
+        let (functions, _) = parse("a=2;a x");
+        assert_eq!(functions[0].vars, ["a=2"]);
+        assert!(matches!(val(&functions[0]), Val::Num(None)));
+        let (functions, _) = parse("a=2;a");
+        assert!(matches!(val(&functions[0]), Val::Num(Some(Complex::Real(r))) if *r == 2.0));
+    }
+    #[test]
+    fn init_prelude_per_function() {
+        let (functions, _) = parse("a=2;a#b=3;a b");
+        assert_eq!(functions[0].vars, ["a=2"]);
+        assert_eq!(functions[1].vars, ["b=3"]);
+        assert!(matches!(val(&functions[0]), Val::Num(Some(Complex::Real(r))) if *r == 2.0));
+        assert!(matches!(val(&functions[1]), Val::Num(Some(Complex::Real(r))) if *r == 6.0));
+    }
+    #[test]
+    fn init_into_parts() {
+        let (functions, _) = parse("a=2;a x#*");
+        let (plots, names, errors) = functions.into_iter().map(Function::into_parts).fold(
+            (Vec::new(), Vec::new(), Vec::new()),
+            |(mut plots, mut names, mut errors), (plot, name, error)| {
+                plots.push(plot);
+                names.push(name);
+                errors.push(error);
+                (plots, names, errors)
+            },
+        );
+        assert!(plots[0].is_some() && plots[1].is_none());
+        assert!(errors[0].is_none());
+        assert_eq!(errors[1].as_ref().unwrap().function, "*");
+        assert_eq!(names, [seg(&["a=2"], "a x"), seg(&[], "*")]);
+    }
+    fn data(function: &str) -> Data {
+        let mut options = Options {
+            prec: 64,
+            graph_prec: 64,
+            graphing: true,
+            ..Options::default()
+        };
+        let vars = get_vars(options);
+        let (functions, _) = init(function, &mut options, vars.clone());
+        let mut data = Data {
+            data: Vec::new(),
+            errors: Vec::new(),
+            deep: None,
+            floor: false,
+            shown: Vec::new(),
+            sources: Vec::new(),
+            changes: None,
+            ruler: None,
+            domain: false,
+            options,
+            vars,
+            blacklist: Vec::new(),
+            var: rupl::types::Vec2::new(0.0, 0.0),
+            count_changed: false,
+            refine: None,
+            budget: Budget::default(),
+            #[cfg(feature = "bincode")]
+            session: None,
+        };
+        data.set_functions(functions);
+        data
+    }
+    #[test]
+    fn limit_kept() {
+        let mut data = data("x{limit=0.5}#x");
+        assert_eq!(data.data[0].as_ref().unwrap().limit.min, 0.5);
+        data.data[1].as_mut().unwrap().limit = Limit {
+            min: 0.25,
+            max: 0.75,
+        };
+        let mut options = data.options;
+        let (functions, _) = init("x^2#x^3", &mut options, data.vars.clone());
+        data.set_functions(functions);
+        assert_eq!(data.data[0].as_ref().unwrap().limit, Limit::default());
+        assert_eq!(
+            data.data[1].as_ref().unwrap().limit,
+            Limit {
+                min: 0.25,
+                max: 0.75
+            }
+        );
+    }
+    #[test]
+    fn limit_follows_reuse() {
+        let mut data = data("x#x^3");
+        let limit = Limit {
+            min: 0.25,
+            max: 0.75,
+        };
+        data.data[0].as_mut().unwrap().limit = limit;
+        let mut options = data.options;
+        let (functions, _) = init_from("x^2#x#x^3", &mut options, data.vars.clone(), &data.sources);
+        data.set_functions(functions);
+        assert_eq!(data.data[0].as_ref().unwrap().limit, Limit::default());
+        assert_eq!(data.data[1].as_ref().unwrap().limit, limit);
+    }
+    #[test]
+    fn argand_from_show() {
+        let mut data = data("e^(ix)#x");
+        let name = |name: &str, show| Name {
+            name: name.to_owned(),
+            show,
+            vars: Vec::new(),
+        };
+        let argand = |data: &Data| data.data[0].as_ref().unwrap().argand;
+        data.shown = vec![Show::Complex, Show::Real];
+        let mut names = [name("e^(ix)", Show::Real), name("x", Show::Real)];
+        assert_eq!(data.toggle_argand(&names), vec![0]);
+        assert!(argand(&data));
+        data.shown = vec![Show::Real, Show::Real];
+        assert!(data.toggle_argand(&names).is_empty());
+        //kept through an edit like a limit
+        let mut options = data.options;
+        let (functions, _) = init("e^(2ix)#x", &mut options, data.vars.clone());
+        data.set_functions(functions);
+        assert!(argand(&data));
+        names[0].show = Show::Complex;
+        assert_eq!(data.toggle_argand(&names), vec![0]);
+        assert!(!argand(&data));
+    }
+    #[test]
+    fn progressive_follows_cost() {
+        let data = data("sin(x)cos(y)");
+        assert!(!data.progressive(PROGRESSIVE - 1));
+        assert!(data.progressive(PROGRESSIVE));
+        data.data[0].as_ref().unwrap().cost.record(1e-3, 1_000_000);
+        assert!(!data.progressive(1 << 20));
+        data.data[0].as_ref().unwrap().cost.record(1.0, 1);
+        assert!(data.progressive(1024));
+    }
+    #[test]
+    fn refine_reuses_coarse_grid() {
+        for function in ["sin(x)cos(y)", "sqrt(x+yi)"] {
+            let data = data(function);
+            let mut direct = Vec::new();
+            data.generate_3d(-2.0, -1.0, 2.0, 3.0, 16, 8, None, &mut direct);
+            let mut refined = Vec::new();
+            data.generate_3d(-2.0, -1.0, 2.0, 3.0, 8, 4, None, &mut refined);
+            data.generate_3d(-2.0, -1.0, 2.0, 3.0, 16, 8, None, &mut refined);
+            let (GraphData::Width3D(a, ..), GraphData::Width3D(b, ..)) = (&direct[0], &refined[0])
+            else {
+                unreachable!()
+            };
+            assert_eq!(a.len(), 17 * 9);
+            assert!(
+                a.iter()
+                    .zip(b.iter())
+                    .all(|(a, b)| a.to_options() == b.to_options())
+            );
+        }
+    }
+    #[test]
+    fn budget_holds_frame_time() {
+        let mut data = data("sin(x)cos(y)");
+        let mut graph = Vec::new();
+        data.generate_3d(-2.0, -2.0, 2.0, 2.0, 16, 16, None, &mut graph);
+        let plot = data.data[0].as_mut().unwrap();
+        assert!(plot.cost.get() > 0.0);
+        plot.cost = Cost(AtomicU64::new(1e-6f64.to_bits()));
+        let mut budget = Budget::default();
+        assert_eq!(budget.grid(plot, 512, 512), (512, 512));
+        budget.share = budget.share(1);
+        assert_eq!(budget.grid(plot, 32, 32), (32, 32));
+        assert_eq!(budget.grid(plot, 512, 512), (181, 181));
+        assert_eq!(budget.samples(plot, 8192), 8192);
+        plot.limit.min = 0.5;
+        assert_eq!(budget.grid(plot, 512, 512), (362, 362));
+        budget.frame = 0.0;
+        assert_eq!(budget.share(1), None);
+    }
+    #[test]
+    fn reusable_unchanged() {
+        let old = split("x#cos(x)#1/x");
+        assert_eq!(reusable(&old, &old), [Some(0), Some(1), Some(2)]);
+        assert_eq!(reusable(&[], &old), [None, None, None]);
+    }
+    #[test]
+    fn reusable_edit() {
+        let old = split("x#cos(x)#1/x");
+        let new = split("x#sin(x)#1/x");
+        assert_eq!(reusable(&old, &new), [Some(0), None, Some(2)]);
+    }
+    #[test]
+    fn reusable_insert_and_remove() {
+        let old = split("x#cos(x)#1/x");
+        let new = split("x#x^2#cos(x)#1/x");
+        assert_eq!(reusable(&old, &new), [Some(0), None, Some(1), Some(2)]);
+        let new = split("x#1/x");
+        assert_eq!(reusable(&old, &new), [Some(0), Some(2)]);
+    }
+    #[test]
+    fn reusable_dependents() {
+        let old = split("a=2;a x#b=a+1;b x#c=3;c x#x");
+        let new = split("a=5;a x#b=a+1;b x#c=3;c x#x");
+        assert_eq!(reusable(&old, &new), [None, None, Some(2), Some(3)]);
+        let new = split("a=2;a x#c=3;c x#x");
+        assert_eq!(reusable(&old, &new), [Some(0), Some(2), Some(3)]);
+    }
+    #[test]
+    fn reusable_whole_names() {
+        let old = split("a=2;a x#tan(x)#gamma(x)#2a");
+        let new = split("a=5;a x#tan(x)#gamma(x)#2a");
+        assert_eq!(reusable(&old, &new), [None, Some(1), Some(2), None]);
+    }
+    #[test]
+    fn reusable_commands() {
+        let old = split("deg;sin(x)#x");
+        let new = split("rad;sin(x)#x");
+        assert_eq!(reusable(&old, &new), [None, None]);
+    }
+    #[test]
+    fn init_from_reuses() {
+        let mut options = Options {
+            prec: 128,
+            graph_prec: 128,
+            graphing: true,
+            ..Options::default()
+        };
+        let vars = get_vars(options);
+        let old = split("x#cos(x)");
+        let (functions, _) = init_from("x#sin(x)", &mut options, vars, &old);
+        assert_eq!(functions[0].reuse, Some(0));
+        assert!(functions[0].graph_type.is_none());
+        assert_eq!(functions[1].reuse, None);
+        assert!(functions[1].graph_type.is_some());
+    }
+    #[test]
+    fn sampling_syntax() {
+        let options = Options::default();
+        let vars = get_vars(options);
+        let parse = |name: &str| sampling(name, options, &vars);
+        assert_eq!(
+            parse("sin(x){0<x<1}"),
+            Some(Ok((
+                Sampling {
+                    x: Some(Range::Within(0.0, 1.0)),
+                    ..Sampling::default()
+                },
+                6
+            )))
+        );
+        assert_eq!(
+            parse("x^2 {samples*4, x>=-1, 2>x}").map(|r| r.unwrap().0),
+            Some(Sampling {
+                x: Some(Range::Within(-1.0, 2.0)),
+                mult: Some(4.0),
+                ..Sampling::default()
+            })
+        );
+        let (s, i) = parse("{cos(x),sin(x)}{0<=x<=pi, y=-1..1}")
+            .unwrap()
+            .unwrap();
+        assert_eq!(i, 15);
+        assert_eq!(s.x, Some(Range::Within(0.0, std::f64::consts::PI)));
+        assert_eq!(s.y, Some(Range::Fixed(-1.0, 1.0)));
+        assert_eq!(parse("{cos(x),sin(x)}"), None);
+        assert_eq!(parse("{x<1}"), None);
+        assert_eq!(parse("x{cos(x),sin(x)}"), None);
+        assert_eq!(
+            parse("x{0<t<1}"),
+            Some(Err(("invalid sampling setting 0<t<1".to_owned(), 2)))
+        );
+        assert_eq!(
+            parse("x{samples*4, samples*0}"),
+            Some(Err(("invalid sampling setting samples*0".to_owned(), 14)))
+        );
+        assert_eq!(
+            parse("x{stem}"),
+            Some(Err(("invalid sampling setting stem".to_owned(), 2)))
+        );
+    }
+    #[test]
+    fn sampling_error() {
+        let (functions, _) = parse("sin(x){t<10}");
+        let error = functions[0].error.as_ref().unwrap();
+        assert_eq!(error.message, "invalid sampling setting t<10");
+        assert_eq!(error.position, Some(7));
+    }
+    #[test]
+    fn sampling_restricts_range() {
+        let data = data("x{0<x<2}#x{x=-1..1,samples*2}#x{x>9}");
+        let mut graph = Vec::new();
+        data.generate_2d(-8.0, 8.0, 16, None, &mut graph);
+        let GraphData::Width(v, s, e) = &graph[0] else {
+            unreachable!()
+        };
+        assert_eq!((v.len(), *s, *e), (3, 0.0, 2.0));
+        let GraphData::Width(v, s, e) = &graph[1] else {
+            unreachable!()
+        };
+        assert_eq!((v.len(), *s, *e), (33, -1.0, 1.0));
+        assert!(matches!(graph[2], GraphData::None));
+        assert!(data.value(1.0, 0.0)[0].is_some());
+        assert!(data.value(3.0, 0.0)[0].is_none());
+    }
+    #[test]
+    fn sequence_points() {
+        let data = data("n^2#a(n)=a(n-1)+a(n-2), a(0)=0, a(1)=1#n{stems}");
+        let mut graph = Vec::new();
+        data.generate_2d(-2.5, 10.5, 512, None, &mut graph);
+        let points = |d: &GraphData| {
+            let GraphData::List(l) = d else {
+                unreachable!()
+            };
+            l.iter()
+                .filter_map(|d| match d {
+                    GraphData::Point(p) => Some((p.x, p.y)),
+                    _ => None,
+                })
+                .collect::<Vec<(f64, f64)>>()
+        };
+        assert_eq!(
+            points(&graph[0]),
+            (-2..=10)
+                .map(|k| (k as f64, (k * k) as f64))
+                .collect::<Vec<(f64, f64)>>()
+        );
+        assert_eq!(
+            points(&graph[1])
+                .into_iter()
+                .map(|(_, v)| v)
+                .collect::<Vec<f64>>(),
+            [0.0, 1.0, 1.0, 2.0, 3.0, 5.0, 8.0, 13.0, 21.0, 34.0, 55.0]
+        );
+        let GraphData::List(stems) = &graph[2] else {
+            unreachable!()
+        };
+        assert_eq!(stems.len(), 26);
+        assert_eq!(
+            data.value(7.0, 0.0)[1].and_then(|c| c.to_options().0),
+            Some(13.0)
+        );
+        assert!(data.value(7.5, 0.0)[1].is_none());
+    }
+    #[test]
+    fn orbits() {
+        let data = data("r=2;x/2{cobweb=1,steps=3}#r x(1-x){bifurcation=r}");
+        assert_eq!(data.orbits(), [(0, vec![1.0, 0.5, 0.25, 0.125])]);
+        let mut graph = Vec::new();
+        data.generate_2d(2.0, 2.5, 1, None, &mut graph);
+        let GraphData::List(cobweb) = &graph[0] else {
+            unreachable!()
+        };
+        let GraphData::Coord(stairs) = &cobweb[2] else {
+            unreachable!()
+        };
+        assert_eq!(
+            stairs
+                .iter()
+                .map(|(x, y)| (*x, y.to_options().0))
+                .collect::<Vec<(f64, Option<f64>)>>(),
+            [
+                (1.0, Some(0.0)),
+                (1.0, Some(0.5)),
+                (0.5, Some(0.5)),
+                (0.5, Some(0.25)),
+                (0.25, Some(0.25)),
+                (0.25, Some(0.125)),
+                (0.125, Some(0.125))
+            ]
+        );
+        let GraphData::List(points) = &graph[1] else {
+            unreachable!()
+        };
+        //r x(1-x) settles on 1-1/r while r<3
+        assert_eq!(points.len(), 2 * 64);
+        assert!(points.iter().all(|p| match p {
+            GraphData::Point(p) => (p.y - (1.0 - 1.0 / p.x)).abs() < 1e-9,
+            _ => false,
+        }));
+    }
+    #[test]
+    fn fractal_counts() {
+        let data = data("z^2+c{fractal=64}#z^2-1{fractal,bailout=4}");
+        let fractal = |i: usize| data.data[i].as_ref().unwrap().sampling.fractal.unwrap();
+        assert_eq!((fractal(0).iterations, fractal(0).julia), (64, false));
+        assert_eq!((fractal(1).bailout, fractal(1).julia), (4.0, true));
+        let count = |x: f64, i: usize| data.value(x, 0.5)[i].and_then(|c| c.to_options().0);
+        assert!(count(0.5, 0).is_some_and(|n| (n - 4.3).abs() < 0.01));
+        let mut graph = Vec::new();
+        data.generate_3d(-2.0, -1.0, 2.0, 1.0, 4, 2, Some(0), &mut graph);
+        let GraphData::Width3D(v, ..) = &graph[0] else {
+            unreachable!()
+        };
+        assert_eq!(v.len(), 15);
+    }
+    #[test]
+    fn conformal_grid() {
+        let data = data("z^2{grid=2}#z{grid=4,polar}");
+        let mut graph = Vec::new();
+        data.generate_2d(-1.0, 1.0, 4, None, &mut graph);
+        let point = |d: &GraphData, line: usize, k: usize| {
+            let GraphData::List(lines) = d else {
+                unreachable!()
+            };
+            let GraphData::Coord(points) = &lines[line] else {
+                unreachable!()
+            };
+            match points[k] {
+                (x, Complex::Real(y)) => (lines.len(), points.len(), x, y),
+                _ => unreachable!(),
+            }
+        };
+        //x=1 meets the real axis at 1, y=-1 passes -i and -1-i which square to -1 and 2i
+        assert_eq!(point(&graph[0], 2, 2), (6, 5, 1.0, 0.0));
+        assert_eq!(point(&graph[0], 3, 2), (6, 5, -1.0, 0.0));
+        assert_eq!(point(&graph[0], 3, 0), (6, 5, 0.0, 2.0));
+        assert_eq!(point(&graph[1], 0, 0), (8, 5, 0.25, 0.0));
+        assert_eq!(point(&graph[1], 4, 4), (8, 5, 1.0, 0.0));
+    }
+    #[test]
+    fn path_images() {
+        let data = data("1/z{circle=1}#z-2{path=3e^(2pi i t), samples*2}#z^2{segment=0..1+i}");
+        let mut graph = Vec::new();
+        data.generate_2d(-1.0, 1.0, 4, None, &mut graph);
+        let windings = data.windings();
+        assert_eq!(windings[..2], [(0, -1.0), (1, 1.0)]);
+        assert_eq!(windings[2].0, 2);
+        assert!((windings[2].1 - 0.25).abs() < 1e-9);
+        let GraphData::List(curves) = &graph[1] else {
+            unreachable!()
+        };
+        let GraphData::Coord(path) = &curves[0] else {
+            unreachable!()
+        };
+        assert_eq!(path.len(), 2 * PATH + 1);
+        assert!(data.value(0.5, 0.5)[0].is_none());
+    }
+    #[cfg(feature = "rug")]
+    #[test]
+    #[cfg(feature = "rug")]
+    fn graph_prec_honoured() {
+        use crate::eval::precise;
+        let mut data = data("x");
+        assert!(!precise(&data.sample_options(0)));
+        data.options.graph_prec = 128;
+        assert!(precise(&data.sample_options(0)));
+        assert_eq!(data.sample_options(0).graph_prec, 128);
+    }
+    #[test]
+    #[cfg(feature = "rug")]
+    fn passed_vars_precise() {
+        let mut options = Options {
+            prec: 128,
+            graph_prec: 128,
+            graphing: true,
+            ..Options::default()
+        };
+        //the variables as kalc passes them with -d, a defined on top of the defaults
+        let mut vars = get_vars(options);
+        let a = "a=3".chars().collect::<Vec<char>>();
+        let _ = set_commands_or_vars(&mut Colors::default(), &mut options, &mut vars, &a);
+        let (functions, _) = init("a x{precise}", &mut options, vars);
+        let (plot, _, error) = functions.into_iter().next().unwrap().into_parts();
+        assert!(error.is_none());
+        let plot = plot.unwrap();
+        //parsed for rug only once asked for
+        assert!(plot.precise.tokens.get().is_none());
+        assert!(matches!(plot.precise.get(), Some(Tokens::Rug(_, _))));
+        let Value::Num(re, _) = plot.eval("x", Coord::new(2.0, 0.0), options) else {
+            unreachable!()
+        };
+        assert_eq!(re, 6.0);
+    }
+    #[test]
+    fn argand_trajectory() {
+        let data = data("e^(ix){argand}");
+        let mut graph = Vec::new();
+        data.generate_2d(0.0, 3.0, 64, None, &mut graph);
+        let GraphData::List(curves) = &graph[0] else {
+            unreachable!()
+        };
+        assert_eq!(curves.len(), 17);
+        let GraphData::Coord(path) = &curves[0] else {
+            unreachable!()
+        };
+        assert_eq!(path.len(), 65);
+        for (x, y) in path {
+            let Complex::Real(y) = y else { unreachable!() };
+            assert!((x.hypot(*y) - 1.0).abs() < 1e-9)
+        }
+        assert!(data.value(1.0, 0.0)[0].is_none());
+    }
+    #[test]
+    fn branch_sheets() {
+        let data = data("sqrt(z){branches}#ln(z){branches=3}");
+        let mut graph = Vec::new();
+        data.generate_3d(-2.0, -2.0, 2.0, 2.0, 4, 4, None, &mut graph);
+        let parts = |c: &Complex| match c {
+            Complex::Real(re) => (*re, 0.0),
+            Complex::Imag(im) => (0.0, *im),
+            Complex::Complex(re, im) => (*re, *im),
+        };
+        let sheets = |d: &GraphData| {
+            let GraphData::List(sheets) = d else {
+                unreachable!()
+            };
+            sheets
+                .iter()
+                .map(|s| {
+                    let GraphData::Width3D(v, _, _, _, _) = s else {
+                        unreachable!()
+                    };
+                    v.iter().map(parts).collect::<Vec<(f64, f64)>>()
+                })
+                .collect::<Vec<Vec<(f64, f64)>>>()
+        };
+        //each sheet is its lower half plane then its upper, the upper half of sheet k meeting the
+        //lower half of sheet k+1 along the negative real axis and its own along the positive
+        let close =
+            |a: (f64, f64), b: (f64, f64)| (a.0 - b.0).abs() < 1e-6 && (a.1 - b.1).abs() < 1e-6;
+        let stitched = |halves: &[Vec<(f64, f64)>]| {
+            for k in 0..halves.len() / 2 - 1 {
+                let (upper, lower, next) = (&halves[2 * k + 1], &halves[2 * k], &halves[2 * k + 2]);
+                assert_eq!((upper.len(), lower.len()), (5 * 3, 5 * 3));
+                //0 itself is left out, where ln is infinite
+                for i in [0, 1, 3, 4] {
+                    let below = if i < 2 { next } else { lower };
+                    assert!(close(upper[i], below[2 * 5 + i]))
+                }
+            }
+        };
+        let whole = |halves: &[Vec<(f64, f64)>]| {
+            halves
+                .chunks(2)
+                .map(|h| h.concat())
+                .collect::<Vec<Vec<(f64, f64)>>>()
+        };
+        //the second sheet of sqrt is the negative of the first
+        let roots = sheets(&graph[0]);
+        assert_eq!(roots.len(), 2 * 2);
+        stitched(&roots);
+        let roots = whole(&roots);
+        for (a, b) in roots[0].iter().zip(&roots[1]) {
+            assert!((a.0 + b.0).abs() < 1e-9 && (a.1 + b.1).abs() < 1e-9)
+        }
+        //each sheet of ln sits 2 pi above the last
+        let logs = sheets(&graph[1]);
+        assert_eq!(logs.len(), 2 * 3);
+        stitched(&logs);
+        let logs = whole(&logs);
+        for k in 1..3 {
+            for (a, b) in logs[k - 1]
+                .iter()
+                .zip(&logs[k])
+                .filter(|(a, _)| a.0.is_finite())
+            {
+                assert!(
+                    (b.0 - a.0).abs() < 1e-9 && (b.1 - a.1 - std::f64::consts::TAU).abs() < 1e-9
+                )
+            }
+        }
+    }
+    #[test]
+    fn riemann_sphere() {
+        let data = data("z{sphere=4}#1/z{sphere=4}");
+        let mut graph = Vec::new();
+        data.generate_3d(-2.0, -2.0, 2.0, 2.0, 8, 8, None, &mut graph);
+        //the south pole is 0 and the north pole infinity, where z sinks to 1/2 and 1/z bulges to 2
+        let poles = |d: &GraphData| {
+            let GraphData::List(curves) = d else {
+                unreachable!()
+            };
+            assert_eq!(curves.len(), 3 + 8);
+            assert!(curves.iter().all(|c| matches!(c, GraphData::Coord3D(_))));
+            let GraphData::Coord3D(meridian) = &curves[3] else {
+                unreachable!()
+            };
+            match (meridian[0].2, meridian[2], meridian[4].2) {
+                (Complex::Real(south), (x, _, Complex::Real(z)), Complex::Real(north)) => {
+                    (south, x.hypot(z), north)
+                }
+                _ => unreachable!(),
+            }
+        };
+        let (south, equator, north) = poles(&graph[0]);
+        assert!((south + 0.5).abs() < 1e-9 && (equator - 1.0).abs() < 1e-9 && north > 1.9);
+        let (south, _, north) = poles(&graph[1]);
+        assert!((south + 2.0).abs() < 1e-9 && north < 0.51);
+    }
+    #[test]
+    fn riemann_sphere_domain() {
+        let plain = data("z^2{sphere=4}");
+        let mut domain = data("z^2{sphere=4}");
+        domain.domain = true;
+        let (mut a, mut b) = (Vec::new(), Vec::new());
+        plain.generate_3d(-2.0, -2.0, 2.0, 2.0, 8, 8, None, &mut a);
+        domain.generate_3d(-2.0, -2.0, 2.0, 2.0, 8, 8, None, &mut b);
+        //still the sphere rather than a flat grid, so it stays a sphere whichever mode is live
+        let vertices = |d: &GraphData| {
+            let GraphData::List(curves) = d else {
+                unreachable!()
+            };
+            curves
+                .iter()
+                .flat_map(|c| {
+                    let GraphData::Coord3D(c) = c else {
+                        unreachable!()
+                    };
+                    c.iter().map(|(x, y, z)| match z {
+                        Complex::Real(z) => (*x, *y, *z),
+                        _ => unreachable!(),
+                    })
+                })
+                .collect::<Vec<(f64, f64, f64)>>()
+        };
+        assert_eq!(vertices(&a[0]), vertices(&b[0]));
+    }
+    #[test]
+    fn zeros_and_poles() {
+        let mut data = data("(z-1)/(z+i)^2");
+        data.domain = true;
+        let mut graph = Vec::new();
+        data.generate_3d(-2.1, -2.1, 1.9, 1.9, 40, 40, None, &mut graph);
+        //left to an idle frame rather than found while sampling
+        assert!(data.singularities().is_empty());
+        data.locate(&mut graph);
+        let found = data.singularities();
+        assert_eq!(found.len(), 1);
+        let (zero, pole) = match found[0].1[..] {
+            [a, b] if a.order > 0 => (a, b),
+            [a, b] => (b, a),
+            _ => unreachable!(),
+        };
+        assert_eq!((zero.order, zero.residue), (1, None));
+        assert!((zero.x - 1.0).abs() < 1e-9 && zero.y.abs() < 1e-9);
+        assert_eq!(pole.order, -2);
+        assert!(pole.x.abs() < 1e-6 && (pole.y + 1.0).abs() < 1e-6);
+        let (re, im) = pole.residue.unwrap();
+        assert!((re - 1.0).abs() < 1e-6 && im.abs() < 1e-6);
+        //the grid followed by a marker at each, replaced rather than added to when run again
+        data.locate(&mut graph);
+        let GraphData::List(list) = &graph[0] else {
+            unreachable!()
+        };
+        assert!(matches!(list[0], GraphData::Width3D(..)));
+        let markers = list[1..]
+            .iter()
+            .map(|d| match d {
+                GraphData::Point(p) => (p.x, p.y),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<(f64, f64)>>();
+        assert_eq!(
+            markers,
+            found[0]
+                .1
+                .iter()
+                .map(|s| (s.x, s.y))
+                .collect::<Vec<(f64, f64)>>()
+        );
+    }
+    #[test]
+    fn zeros_and_poles_on_nodes() {
+        //the symmetric view samples exactly at the double zero at 0 and the pole at 1
+        let mut data = data("z^2/(z-1)");
+        data.domain = true;
+        let mut graph = Vec::new();
+        data.generate_3d(-2.0, -2.0, 2.0, 2.0, 8, 8, None, &mut graph);
+        data.locate(&mut graph);
+        let found = data.singularities();
+        assert_eq!(found.len(), 1);
+        let (zero, pole) = match found[0].1[..] {
+            [a, b] if a.order > 0 => (a, b),
+            [a, b] => (b, a),
+            _ => unreachable!(),
+        };
+        assert_eq!(zero.order, 2);
+        assert!(zero.x.abs() < 1e-6 && zero.y.abs() < 1e-6);
+        assert_eq!(pole.order, -1);
+        assert!((pole.x - 1.0).abs() < 1e-6 && pole.y.abs() < 1e-6);
+        let (re, im) = pole.residue.unwrap();
+        assert!((re - 1.0).abs() < 1e-6 && im.abs() < 1e-6);
+        let pole = crate::singular::Singularity {
+            x: 1.0,
+            y: 0.5,
+            order: -1,
+            residue: Some((1.0, -0.25)),
+        };
+        assert_eq!(
+            pole.label(),
+            "pole of order 1 at 1.000000+0.500000i, residue 1.000000-0.250000i"
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use crate::compile::Compiled;
+use crate::data::{Coord, Plot};
+use crate::{C, F, I};
+#[cfg(feature = "rug")]
+use crate::{RC, RF, RI};
+use kalc_lib::complex::NumStr;
+use kalc_lib::complex::NumStr::{Num, Vector};
+use kalc_lib::math::do_math;
+use kalc_lib::misc::{place_funcvar, place_var};
+use kalc_lib::parse::simplify;
+use kalc_lib::types::{Complex as Comp, Float, FloatShared};
+use kalc_lib::units::{Number, Options};
+use rupl::types::Complex;
+//graph_prec above this evaluates with rug instead of f64, as does a view too deep for f64, which
+//sample_options raises graph_prec for
+#[cfg(feature = "rug")]
+pub(crate) const F64_PREC: u32 = 64;
+//extra bits so neighbouring samples stay distinct after a few operations
+const GUARD_BITS: u32 = 8;
+#[cfg(feature = "rug")]
+pub(crate) fn precise(options: &Options) -> bool {
+    options.graph_prec > F64_PREC
+}
+//bits needed to keep len samples between start and end apart
+pub(crate) fn sample_bits(start: f64, end: f64, len: usize) -> u32 {
+    let width = (end - start).abs();
+    if width == 0.0 || !width.is_finite() {
+        return 0;
+    }
+    let centre = ((start + end) / 2.0).abs().max(width);
+    ((centre / width).log2() + (len.max(1) as f64).log2()).ceil() as u32 + GUARD_BITS
+}
+//a view only this many f64 steps of its centre wide is at the zoom limit, its bounds are f64 so
+//no sample precision resolves anything narrower
+const FLOOR_STEPS: f64 = 64.0;
+pub(crate) fn floor(start: f64, end: f64) -> bool {
+    let centre = ((start + end) / 2.0).abs();
+    (end - start).abs() <= FLOOR_STEPS * centre * f64::EPSILON
+}
+//the precision to sample with when f64 can no longer tell samples apart
+pub(crate) fn deep(bits: u32) -> Option<u32> {
+    (bits > f64::MANTISSA_DIGITS).then_some(bits)
+}
+#[cfg(feature = "rug")]
+impl Coord {
+    pub(crate) fn rug(&self, options: &Options) -> Number<RI, RF, RC> {
+        let start = Number::<RI, RF, RC>::from_f64(self.start, options);
+        if self.offset == 0.0 {
+            start
+        } else {
+            let offset = Number::<RI, RF, RC>::from_f64(self.offset, options);
+            Number::from(start.number + offset.number, None)
+        }
+    }
+}
+pub(crate) enum Value {
+    Num(f64, f64),
+    Vector(Vec<(f64, f64)>),
+    Error(String),
+    None,
+}
+impl Value {
+    pub(crate) fn complex(&self) -> Complex {
+        if let Value::Num(re, im) = self {
+            Complex::Complex(*re, *im)
+        } else {
+            Complex::Complex(f64::NAN, f64::NAN)
+        }
+    }
+}
+#[allow(clippy::type_complexity)]
+#[derive(Clone, Debug)]
+pub(crate) enum Tokens {
+    F64(Vec<NumStr<I, F, C>>, Vec<(String, Vec<NumStr<I, F, C>>)>),
+    Compiled(Compiled),
+    #[cfg(feature = "rug")]
+    Rug(
+        Vec<NumStr<RI, RF, RC>>,
+        Vec<(String, Vec<NumStr<RI, RF, RC>>)>,
+    ),
+}
+macro_rules! value {
+    ($result:expr) => {
+        match $result {
+            Ok(Num(n)) => Value::Num(n.number.real().to_f64(), n.number.imag().to_f64()),
+            Ok(Vector(v)) => Value::Vector(
+                v.iter()
+                    .map(|n| (n.number.real().to_f64(), n.number.imag().to_f64()))
+                    .collect(),
+            ),
+            Err(e) => Value::Error(e.to_string()),
+            _ => Value::None,
+        }
+    };
+}
+macro_rules! eval {
+    ($func:expr, $funcvar:expr, $var:expr, $v:expr, $options:expr) => {{
+        let v = NumStr::new($v);
+        value!(do_math(
+            place_var($func.clone(), $var, v.clone()),
+            $options,
+            place_funcvar($funcvar.clone(), $var, v),
+        ))
+    }};
+}
+macro_rules! bind {
+    ($func:expr, $funcvar:expr, $var:expr, $v:expr, $options:expr, $wrap:path) => {{
+        let v = NumStr::new($v);
+        let mut func = place_var($func.clone(), $var, v.clone());
+        let mut funcvar = place_funcvar($funcvar.clone(), $var, v);
+        simplify(&mut func, &mut funcvar, $options);
+        $wrap(func, funcvar)
+    }};
+}
+impl Tokens {
+    pub(crate) fn eval(&self, var: &str, c: Coord, options: Options) -> Value {
+        match self {
+            Tokens::F64(func, funcvar) => {
+                eval!(
+                    func,
+                    funcvar,
+                    var,
+                    Number::from_f64(c.get(), &options),
+                    options
+                )
+            }
+            Tokens::Compiled(compiled) => compiled.eval(var, c.get()),
+            #[cfg(feature = "rug")]
+            Tokens::Rug(func, funcvar) => eval!(func, funcvar, var, c.rug(&options), options),
+        }
+    }
+    pub(crate) fn bind(&self, var: &str, c: Coord, options: Options) -> Tokens {
+        match self {
+            Tokens::F64(func, funcvar) => bind!(
+                func,
+                funcvar,
+                var,
+                Number::from_f64(c.get(), &options),
+                options,
+                Tokens::F64
+            ),
+            Tokens::Compiled(compiled) => Tokens::Compiled(compiled.bind(var, c.get())),
+            #[cfg(feature = "rug")]
+            Tokens::Rug(func, funcvar) => {
+                bind!(func, funcvar, var, c.rug(&options), options, Tokens::Rug)
+            }
+        }
+    }
+}
+impl Plot {
+    pub(crate) fn eval(&self, var: &str, c: Coord, options: Options) -> Value {
+        #[cfg(feature = "rug")]
+        if (precise(&options) || self.sampling.precise)
+            && let Some(precise) = self.precise.get()
+        {
+            return precise.eval(var, c, options);
+        }
+        if let Some(compiled) = &self.compiled {
+            return compiled.eval(var, c.get());
+        }
+        eval!(
+            self.func,
+            self.funcvar,
+            var,
+            Number::from_f64(c.get(), &options),
+            options
+        )
+    }
+    pub(crate) fn bind(&self, var: &str, c: Coord, options: Options) -> Tokens {
+        #[cfg(feature = "rug")]
+        if (precise(&options) || self.sampling.precise)
+            && let Some(precise) = self.precise.get()
+        {
+            return precise.bind(var, c, options);
+        }
+        if let Some(compiled) = &self.compiled {
+            return Tokens::Compiled(compiled.bind(var, c.get()));
+        }
+        bind!(
+            self.func,
+            self.funcvar,
+            var,
+            Number::from_f64(c.get(), &options),
+            options,
+            Tokens::F64
+        )
+    }
+}
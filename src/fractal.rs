@@ -0,0 +1,174 @@
+#[cfg(not(feature = "rayon"))]
+use crate::IntoIter;
+use crate::data::{Coord, Data, Fractal, Plot};
+#[cfg(feature = "rug")]
+use crate::eval::{Tokens, precise};
+#[cfg(feature = "rug")]
+use crate::{RC, RF, RI};
+#[cfg(feature = "rug")]
+use kalc_lib::complex::NumStr;
+#[cfg(feature = "rug")]
+use kalc_lib::math::do_math;
+#[cfg(feature = "rug")]
+use kalc_lib::misc::{place_funcvar, place_var};
+#[cfg(feature = "rug")]
+use kalc_lib::types::{Complex as Comp, Float, FloatShared};
+#[cfg(feature = "rug")]
+use kalc_lib::units::Number;
+use kalc_lib::units::Options;
+#[cfg(feature = "rayon")]
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use rupl::types::Complex;
+//steps closer than this relative to |z| count as settled, how newton iterations end
+const SETTLED: f64 = 1e-9;
+//the smooth escape count of the orbit z0, step(), step(), ..., if it converges instead the step it
+//settled on plus i times the angle of where, so the basins of newton's method colour apart,
+//NaN if it does neither within the iterations
+fn count(
+    fractal: &Fractal,
+    z0: (f64, f64),
+    mut step: impl FnMut() -> Option<(f64, f64)>,
+) -> Complex {
+    let (mut prev, mut last) = (z0, z0.0.hypot(z0.1).ln());
+    for k in 1..=fractal.iterations {
+        let Some(z) = step() else {
+            return Complex::Real(f64::NAN);
+        };
+        let r = z.0.hypot(z.1);
+        if !r.is_finite() {
+            return Complex::Real(k as f64);
+        }
+        if r > fractal.bailout {
+            //ln|z| grows by about the degree of f each step, which interpolates between counts
+            let degree = r.ln() / last;
+            return Complex::Real(if last > 0.0 && degree > 1.0 {
+                k as f64 - (r.ln() / fractal.bailout.ln()).ln() / degree.ln()
+            } else {
+                k as f64
+            });
+        }
+        if (z.0 - prev.0).hypot(z.1 - prev.1) <= SETTLED * r.max(1.0) {
+            return Complex::Complex(k as f64, z.1.atan2(z.0));
+        }
+        (prev, last) = (z, r.ln())
+    }
+    Complex::Real(f64::NAN)
+}
+impl Data {
+    //the escape counts over a grid, at the precision deep zoom asks for when f has a precise form
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn get_fractal(
+        &self,
+        data: &Plot,
+        fractal: &Fractal,
+        startx: f64,
+        starty: f64,
+        dx: f64,
+        dy: f64,
+        lenx: usize,
+        leny: usize,
+        options: Options,
+    ) -> Vec<Complex> {
+        (0..=leny)
+            .into_par_iter()
+            .flat_map(|j| {
+                let y = Coord::new(starty, j as f64 * dy);
+                (0..=lenx)
+                    .map(|i| {
+                        let x = Coord::new(startx, i as f64 * dx);
+                        self.escape(data, fractal, x, y, options)
+                    })
+                    .collect::<Vec<Complex>>()
+            })
+            .collect()
+    }
+    #[cfg_attr(not(feature = "rug"), allow(unused_variables))]
+    fn escape(
+        &self,
+        data: &Plot,
+        fractal: &Fractal,
+        x: Coord,
+        y: Coord,
+        options: Options,
+    ) -> Complex {
+        //only a deep view, a graph_prec above f64 or {precise} pays for rug, the rest of the grid
+        //steps compiled f64
+        #[cfg(feature = "rug")]
+        if (precise(&options) || data.sampling.precise)
+            && let Some(Tokens::Rug(func, funcvar)) = data.precise.get()
+        {
+            let point = {
+                let (x, y) = (x.rug(&options), y.rug(&options));
+                let i = RC::with_val(x.number.prec(), (0, 1));
+                Number::<RI, RF, RC>::from(x.number + y.number * i, None)
+            };
+            let c = NumStr::new(point.clone());
+            let (func, funcvar) = (
+                place_var(func.clone(), "y", c.clone()),
+                place_funcvar(funcvar.clone(), "y", c),
+            );
+            let mut z = if fractal.julia {
+                point
+            } else {
+                Number::from_f64(0.0, &options)
+            };
+            let z0 = (z.number.real().to_f64(), z.number.imag().to_f64());
+            return count(fractal, z0, || {
+                let v = NumStr::new(z.clone());
+                let Ok(NumStr::Num(n)) = do_math(
+                    place_var(func.clone(), "x", v.clone()),
+                    options,
+                    place_funcvar(funcvar.clone(), "x", v),
+                ) else {
+                    return None;
+                };
+                z = *n;
+                Some((z.number.real().to_f64(), z.number.imag().to_f64()))
+            });
+        }
+        let compiled = data.compiled.as_ref();
+        let c = (x.get(), y.get());
+        let mut z = if fractal.julia { c } else { (0.0, 0.0) };
+        count(fractal, z, || {
+            z = compiled?.step(z, c);
+            Some(z)
+        })
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn smooth_count() {
+        let fractal = Fractal::default();
+        let square = |c: (f64, f64)| {
+            let mut z = (0.0, 0.0);
+            match count(&fractal, z, move || {
+                z = (z.0 * z.0 - z.1 * z.1 + c.0, 2.0 * z.0 * z.1 + c.1);
+                Some(z)
+            }) {
+                Complex::Real(n) => (n, None),
+                Complex::Complex(n, angle) => (n, Some(angle)),
+                Complex::Imag(_) => unreachable!(),
+            }
+        };
+        assert!(square((-1.0, 0.0)).0.is_nan());
+        assert!(square((0.25, 0.0)).0.is_nan());
+        let (a, b) = (square((0.5, 0.5)), square((0.5001, 0.5)));
+        assert!((a.0 - 4.3).abs() < 0.01 && (a.0 - b.0).abs() < 0.01);
+        assert_eq!(square((2.0, 2.0)), (1.0, None));
+        assert_eq!(square((0.0, 0.0)), (1.0, Some(0.0)));
+        let mut z = (1.0, 1.0);
+        let newton = count(&fractal, z, || {
+            //z-(z^2-1)/(2z) settles on 1 from the right half plane
+            let (re, im) = z;
+            let d = 2.0 * (re * re + im * im);
+            z = (re / 2.0 + re / d, im / 2.0 - im / d);
+            Some(z)
+        });
+        let Complex::Complex(steps, angle) = newton else {
+            unreachable!()
+        };
+        assert!(steps < 64.0 && angle.abs() < 1e-6);
+    }
+}
@@ -0,0 +1,141 @@
+use crate::App;
+use kalc_lib::load_vars::get_vars;
+use kalc_lib::units::{GraphType, Options};
+use std::path::PathBuf;
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+//a pixel counts as changed when its luma moves by more than this out of 255
+const LUMA_TOLERANCE: f64 = 8.0;
+//fraction of changed pixels allowed before an image is considered different
+const PIXEL_TOLERANCE: f64 = 0.002;
+const CORPUS: &[(&str, &str, Option<GraphType>)] = &[
+    ("plot_2d", "sin(x)", None),
+    ("plot_2d_complex", "sqrt(x)", None),
+    ("plot_2d_many", "x^2#cos(x)#1/x", None),
+    ("inverse", "x=y^2", None),
+    ("parametric", "{cos(x),sin(2x)}", None),
+    ("parametric_3d", "{cos(x),sin(x),x/4}", None),
+    ("list_plusminus", "±sqrt(x)", None),
+    ("list_solve", "quadratic(1,x,-1)", None),
+    ("surface", "sin(x)cos(y)", None),
+    ("surface_complex", "sqrt(x+yi)", None),
+    ("domain", "sin(1/z)", Some(GraphType::Domain)),
+    ("domain_alt", "(z^2-1)/(z^2+1)", Some(GraphType::DomainAlt)),
+    ("matrix_2d", "{{0,0},{1,2},{2,1},{3,3}}", None),
+    ("matrix_3d", "{{0,0,0},{1,2,1},{2,1,3}}", None),
+];
+//set to rewrite the stored images from the current renders instead of comparing against them
+const BLESS: &str = "KALC_PLOT_BLESS";
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+}
+fn render(function: &str, graphtype: Option<GraphType>) -> Vec<u8> {
+    let mut options = Options {
+        prec: 128,
+        graph_prec: 64,
+        graphing: true,
+        ..Options::default()
+    };
+    if let Some(graphtype) = graphtype {
+        options.graphtype = graphtype
+    }
+    let mut app = App::new(
+        function.to_string(),
+        kalc_lib::units::Data {
+            vars: get_vars(options),
+            options,
+            colors: Default::default(),
+        },
+    );
+    app.render_png(WIDTH, HEIGHT)
+}
+fn decode(bytes: &[u8]) -> (u32, u32, Vec<f64>) {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().unwrap();
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).unwrap();
+    buf.truncate(info.buffer_size());
+    let luma = match info.color_type {
+        png::ColorType::Rgba | png::ColorType::Rgb => buf
+            .chunks(info.color_type.samples())
+            .map(|p| 0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64)
+            .collect(),
+        png::ColorType::GrayscaleAlpha | png::ColorType::Grayscale => buf
+            .chunks(info.color_type.samples())
+            .map(|p| p[0] as f64)
+            .collect(),
+        png::ColorType::Indexed => unreachable!(),
+    };
+    (info.width, info.height, luma)
+}
+fn difference(a: &[u8], b: &[u8]) -> Option<f64> {
+    let (aw, ah, a) = decode(a);
+    let (bw, bh, b) = decode(b);
+    if (aw, ah) != (bw, bh) {
+        return None;
+    }
+    let changed = a
+        .iter()
+        .zip(b.iter())
+        .filter(|(a, b)| (*a - *b).abs() > LUMA_TOLERANCE)
+        .count();
+    Some(changed as f64 / a.len().max(1) as f64)
+}
+fn check(name: &str) {
+    let (_, function, graphtype) = CORPUS.iter().find(|(n, _, _)| *n == name).unwrap();
+    let actual = render(function, *graphtype);
+    let path = golden_dir().join(format!("{name}.png"));
+    if std::env::var_os(BLESS).is_some() {
+        std::fs::create_dir_all(golden_dir()).unwrap();
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read(&path).unwrap_or_else(|e| {
+        panic!(
+            "no golden image for {name} at {} ({e}), run the tests with {BLESS}=1 to write it",
+            path.display()
+        )
+    });
+    let diff = difference(&expected, &actual);
+    if diff.is_none_or(|d| d > PIXEL_TOLERANCE) {
+        let out = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("target")
+            .join("golden");
+        std::fs::create_dir_all(&out).unwrap();
+        std::fs::write(out.join(format!("{name}.png")), actual).unwrap();
+        panic!(
+            "{name} ({function}) differs from {} by {}, actual image written to {}, run the tests with {BLESS}=1 if the change is intended",
+            path.display(),
+            diff.map(|d| format!("{:.2}% of pixels", d * 100.0))
+                .unwrap_or("size".to_owned()),
+            out.display()
+        )
+    }
+}
+macro_rules! golden {
+    ($($name:ident),*) => {
+        $(#[test]
+        fn $name() {
+            check(stringify!($name))
+        })*
+    };
+}
+golden!(
+    plot_2d,
+    plot_2d_complex,
+    plot_2d_many,
+    inverse,
+    parametric,
+    parametric_3d,
+    list_plusminus,
+    list_solve,
+    surface,
+    surface_complex,
+    domain,
+    domain_alt,
+    matrix_2d,
+    matrix_3d
+);
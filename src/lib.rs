@@ -1,6 +1,51 @@
 #![windows_subsystem = "windows"]
+#![cfg_attr(test, feature(test))]
 mod app;
+#[cfg(feature = "kalc-lib")]
+mod argand;
+#[cfg(test)]
+#[cfg(feature = "kalc-lib")]
+mod bench;
+#[cfg(feature = "kalc-lib")]
+mod branch;
+#[cfg(feature = "kalc-lib")]
+mod compile;
+#[cfg(feature = "kalc-lib")]
+mod conformal;
+#[cfg(feature = "kalc-lib")]
+#[cfg(not(target_arch = "wasm32"))]
+mod control;
 mod data;
+#[cfg(feature = "kalc-lib")]
+mod eval;
+#[cfg(feature = "kalc-lib")]
+mod fractal;
+#[cfg(test)]
+#[cfg(feature = "kalc-lib")]
+#[cfg(feature = "tiny-skia")]
+mod golden;
+#[cfg(feature = "bincode")]
+#[cfg(feature = "kalc-lib")]
+mod live;
+#[cfg(feature = "kalc-lib")]
+mod orbit;
+#[cfg(feature = "kalc-lib")]
+mod path;
+#[cfg(feature = "bincode")]
+mod persist;
+#[cfg(feature = "bincode")]
+#[cfg(feature = "kalc-lib")]
+#[cfg(any(feature = "skia", feature = "tiny-skia", feature = "wasm-draw"))]
+mod replay;
+#[cfg(feature = "kalc-lib")]
+mod sequence;
+#[cfg(feature = "bincode")]
+#[cfg(feature = "kalc-lib")]
+mod session;
+#[cfg(feature = "kalc-lib")]
+mod singular;
+#[cfg(feature = "kalc-lib")]
+mod sphere;
 #[cfg(any(feature = "skia", feature = "tiny-skia", feature = "wasm-draw"))]
 mod window;
 use crate::data::Data;
@@ -13,9 +58,6 @@ use rupl::types::{Complex, Graph, GraphData, Name, Show};
 use serde::{Deserialize, Serialize};
 use std::env::args;
 #[cfg(feature = "kalc-lib")]
-#[cfg(feature = "bincode")]
-use std::io::Read;
-#[cfg(feature = "kalc-lib")]
 #[cfg(any(feature = "skia", feature = "tiny-skia"))]
 use std::io::Write;
 #[cfg(feature = "wasm")]
@@ -25,12 +67,12 @@ extern crate wee_alloc;
 #[cfg(feature = "wee")]
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
-//#[cfg(feature = "kalc-lib")]
-//pub type I = kalc_lib::rug::Integer;
-//#[cfg(feature = "kalc-lib")]
-//pub type F = kalc_lib::rug::Float;
-//#[cfg(feature = "kalc-lib")]
-//pub type C = kalc_lib::rug::Complex;
+#[cfg(feature = "rug")]
+pub type RI = kalc_lib::rug::Integer;
+#[cfg(feature = "rug")]
+pub type RF = kalc_lib::rug::Float;
+#[cfg(feature = "rug")]
+pub type RC = kalc_lib::rug::Complex;
 #[cfg(feature = "kalc-lib")]
 pub type I = kalc_lib::types::f64::Integer<i128>;
 #[cfg(feature = "kalc-lib")]
@@ -53,6 +95,62 @@ pub fn main() {
     if !args.is_empty() {
         args.remove(0);
     }
+    #[cfg(feature = "bincode")]
+    #[cfg(feature = "kalc-lib")]
+    if args.first().is_some_and(|a| a == "--sessions") {
+        for name in session::recent() {
+            println!("{name}")
+        }
+        return;
+    }
+    #[cfg(feature = "bincode")]
+    #[cfg(feature = "kalc-lib")]
+    let session = if let Some(i) = args.iter().position(|a| a == "--session")
+        && i + 1 < args.len()
+    {
+        let name = args.remove(i + 1);
+        args.remove(i);
+        Some(name)
+    } else {
+        None
+    };
+    #[cfg(feature = "kalc-lib")]
+    #[cfg(not(target_arch = "wasm32"))]
+    let socket = if let Some(i) = args.iter().position(|a| a.starts_with("--socket")) {
+        let arg = args.remove(i);
+        let addr = arg
+            .strip_prefix("--socket=")
+            .map(|a| a.to_owned())
+            .or_else(control::default_addr);
+        if addr.is_none() {
+            eprintln!("the temporary directory is not a usable socket path, pass --socket=path")
+        }
+        addr
+    } else {
+        None
+    };
+    #[cfg(feature = "bincode")]
+    #[cfg(feature = "kalc-lib")]
+    let live = if let Some(i) = args.iter().position(|a| a == "--live") {
+        args.remove(i);
+        true
+    } else {
+        false
+    };
+    #[cfg(feature = "bincode")]
+    #[cfg(feature = "kalc-lib")]
+    #[cfg(any(feature = "skia", feature = "tiny-skia", feature = "wasm-draw"))]
+    let record = args
+        .iter()
+        .position(|a| a.starts_with("--record="))
+        .map(|i| args.remove(i)["--record=".len()..].to_owned());
+    #[cfg(feature = "bincode")]
+    #[cfg(feature = "kalc-lib")]
+    #[cfg(any(feature = "skia", feature = "tiny-skia"))]
+    let replay = args
+        .iter()
+        .position(|a| a.starts_with("--replay="))
+        .map(|i| args.remove(i)["--replay=".len()..].to_owned());
     #[cfg(not(feature = "wasm"))]
     let s = String::new();
     #[cfg(not(feature = "wasm"))]
@@ -74,11 +172,18 @@ pub fn main() {
         #[cfg(feature = "bincode")]
         {
             let mut stdin = std::io::stdin().lock();
-            let len = &mut [0; 8];
-            stdin.read_exact(len).unwrap();
-            let mut data = Vec::with_capacity(usize::from_be_bytes(*len));
-            stdin.read_to_end(&mut data).unwrap();
-            let mut data: kalc_lib::units::Data<I, F, C> = bitcode::deserialize(&data).unwrap();
+            let data = if live {
+                persist::read_frame(&mut stdin)
+            } else {
+                persist::read_stream(&mut stdin)
+            };
+            let mut data: kalc_lib::units::Data<I, F, C> = match data {
+                Ok(data) => data,
+                Err(e) => {
+                    eprintln!("failed to read data from stdin: {e}");
+                    std::process::exit(1)
+                }
+            };
             data.options.prec = data.options.graph_prec;
             data
         }
@@ -89,7 +194,8 @@ pub fn main() {
     } else {
         let options = Options {
             prec: 128,
-            graph_prec: 128,
+            //f64 until a larger graph_prec, {precise} or a deep view asks for rug
+            graph_prec: 64,
             graphing: true,
             ..Options::default()
         };
@@ -126,9 +232,28 @@ pub fn main() {
                     .insert(0, "notosans".to_owned());
                 cc.egui_ctx.set_fonts(fonts);
                 #[cfg(feature = "kalc-lib")]
-                let app = App::new(function.to_string(), data);
+                #[allow(unused_mut)]
+                let mut app = App::new(function.to_string(), data);
                 #[cfg(not(feature = "kalc-lib"))]
                 let app = App::new(function.to_string());
+                #[cfg(feature = "bincode")]
+                #[cfg(feature = "kalc-lib")]
+                {
+                    app.start_session(session);
+                    if live {
+                        let ctx = cc.egui_ctx.clone();
+                        app.live = Some(live::spawn(move || ctx.request_repaint()));
+                    }
+                }
+                #[cfg(feature = "kalc-lib")]
+                #[cfg(not(target_arch = "wasm32"))]
+                if let Some(socket) = socket {
+                    let ctx = cc.egui_ctx.clone();
+                    match control::spawn(socket, move || ctx.request_repaint()) {
+                        Ok(control) => app.control = Some(control),
+                        Err(e) => eprintln!("failed to open control socket: {e}"),
+                    }
+                }
                 Ok(Box::new(app))
             }),
         )
@@ -144,6 +269,31 @@ pub fn main() {
         let mut app = App::new(function.to_string(), data);
         #[cfg(not(feature = "kalc-lib"))]
         let mut app = App::new(function.to_string());
+        #[cfg(feature = "bincode")]
+        #[cfg(feature = "kalc-lib")]
+        app.start_session(session);
+        #[cfg(feature = "bincode")]
+        #[cfg(feature = "kalc-lib")]
+        {
+            app.recorder = record.map(|path| replay::Recorder::new(path.into()));
+        }
+        #[cfg(feature = "bincode")]
+        #[cfg(feature = "kalc-lib")]
+        #[cfg(any(feature = "skia", feature = "tiny-skia"))]
+        if let Some(path) = replay {
+            match replay::load(path.as_ref()) {
+                Ok(frames) => {
+                    let bytes = app.replay_png(&frames);
+                    if f.is_empty() || f == "-" {
+                        std::io::stdout().lock().write_all(&bytes).unwrap()
+                    } else {
+                        std::fs::write(f, bytes).unwrap()
+                    }
+                }
+                Err(e) => eprintln!("failed to load recording: {e}"),
+            }
+            return;
+        }
         #[cfg(not(feature = "kalc-lib"))]
         {
             let event_loop = winit::event_loop::EventLoop::new().unwrap();
@@ -152,35 +302,35 @@ pub fn main() {
         #[cfg(feature = "kalc-lib")]
         if f.is_empty() {
             let event_loop = winit::event_loop::EventLoop::new().unwrap();
-            event_loop.run_app(&mut app).unwrap()
-        } else {
-            app.plot.set_screen(width as f64, height as f64, true, true);
-            app.plot.mult = 1.0;
-            app.plot.disable_lines = true;
-            app.plot.disable_axis = true;
-            app.data.update(&mut app.plot);
-            #[cfg(feature = "skia")]
-            {
-                let bytes = app.plot.get_png(width as u32, height as u32);
-                if f == "-" {
-                    std::io::stdout()
-                        .lock()
-                        .write_all(bytes.as_bytes())
-                        .unwrap()
-                } else {
-                    std::fs::write(f, bytes.as_bytes()).unwrap()
+            #[cfg(not(target_arch = "wasm32"))]
+            let proxy = event_loop.create_proxy();
+            #[cfg(feature = "bincode")]
+            #[cfg(not(target_arch = "wasm32"))]
+            if live {
+                let proxy = proxy.clone();
+                app.live = Some(live::spawn(move || {
+                    let _ = proxy.send_event(());
+                }));
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(socket) = socket {
+                let wake = move || {
+                    let _ = proxy.send_event(());
+                };
+                match control::spawn(socket, wake) {
+                    Ok(control) => app.control = Some(control),
+                    Err(e) => eprintln!("failed to open control socket: {e}"),
                 }
             }
-            #[cfg(feature = "tiny-skia")]
+            event_loop.run_app(&mut app).unwrap()
+        } else {
+            #[cfg(any(feature = "skia", feature = "tiny-skia"))]
             {
-                let bytes = &app.plot.get_png(width as u32, height as u32);
+                let bytes = app.render_png(width as u32, height as u32);
                 if f == "-" {
-                    std::io::stdout()
-                        .lock()
-                        .write_all(bytes.as_bytes())
-                        .unwrap()
+                    std::io::stdout().lock().write_all(&bytes).unwrap()
                 } else {
-                    std::fs::write(f, bytes.as_bytes()).unwrap()
+                    std::fs::write(f, bytes).unwrap()
                 }
             }
         }
@@ -217,6 +367,28 @@ struct App {
     last_touch_positions: std::collections::HashMap<u64, rupl::types::Vec2>,
     #[cfg(feature = "wasm")]
     dpr: f64,
+    #[cfg(feature = "bincode")]
+    #[cfg(feature = "kalc-lib")]
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    session: Option<String>,
+    //enter was pressed since the last frame, committing a session command
+    #[cfg(feature = "bincode")]
+    #[cfg(feature = "kalc-lib")]
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    commit: bool,
+    #[cfg(feature = "bincode")]
+    #[cfg(feature = "kalc-lib")]
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    live: Option<std::sync::mpsc::Receiver<live::Message>>,
+    #[cfg(feature = "kalc-lib")]
+    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    control: Option<control::Control>,
+    #[cfg(feature = "bincode")]
+    #[cfg(feature = "kalc-lib")]
+    #[cfg(any(feature = "skia", feature = "tiny-skia", feature = "wasm-draw"))]
+    #[cfg_attr(feature = "bincode", serde(skip))]
+    recorder: Option<replay::Recorder>,
 }
 
 #[cfg(feature = "egui")]
@@ -296,13 +468,10 @@ pub(crate) fn get_names(graph: &[GraphData], names: &[(Vec<String>, String)]) ->
         .collect()
 }
 #[cfg(not(feature = "rayon"))]
-use crate::data::Plot;
-#[cfg(not(feature = "rayon"))]
 #[cfg(feature = "kalc-lib")]
-use kalc_lib::complex::NumStr;
+use crate::data::Function;
 #[cfg(not(feature = "rayon"))]
-#[cfg(feature = "kalc-lib")]
-use kalc_lib::units::HowGraphing;
+use crate::data::Plot;
 #[cfg(not(feature = "rayon"))]
 pub trait IntoIter<T: ?Sized> {
     fn into_par_iter(self) -> T;
@@ -323,7 +492,8 @@ impl_into_iter!(
         std::ops::RangeInclusive<usize>,
         std::ops::RangeInclusive<usize>
     ),
-    (std::ops::Range<usize>, std::ops::Range<usize>)
+    (std::ops::Range<usize>, std::ops::Range<usize>),
+    (Vec<f64>, std::vec::IntoIter<f64>)
 );
 #[cfg(not(feature = "rayon"))]
 impl<'a> IntoIter<core::slice::IterMut<'a, rupl::types::GraphData>>
@@ -334,40 +504,9 @@ impl<'a> IntoIter<core::slice::IterMut<'a, rupl::types::GraphData>>
     }
 }
 #[cfg(not(feature = "rayon"))]
-impl<'a> IntoIter<std::vec::IntoIter<&'a str>> for Vec<&'a str> {
-    fn into_par_iter(self) -> std::vec::IntoIter<&'a str> {
-        self.into_iter()
-    }
-}
-#[cfg(not(feature = "rayon"))]
 #[cfg(feature = "kalc-lib")]
-impl
-    IntoIter<
-        std::vec::IntoIter<(
-            String,
-            Vec<NumStr<I, F, C>>,
-            Vec<(String, Vec<NumStr<I, F, C>>)>,
-            HowGraphing,
-            bool,
-        )>,
-    >
-    for Vec<(
-        String,
-        Vec<NumStr<I, F, C>>,
-        Vec<(String, Vec<NumStr<I, F, C>>)>,
-        HowGraphing,
-        bool,
-    )>
-{
-    fn into_par_iter(
-        self,
-    ) -> std::vec::IntoIter<(
-        String,
-        Vec<NumStr<I, F, C>>,
-        Vec<(String, Vec<NumStr<I, F, C>>)>,
-        HowGraphing,
-        bool,
-    )> {
+impl IntoIter<std::vec::IntoIter<Function>> for Vec<Function> {
+    fn into_par_iter(self) -> std::vec::IntoIter<Function> {
         self.into_iter()
     }
 }
@@ -0,0 +1,90 @@
+use crate::app::tab_complete;
+use crate::persist;
+use crate::{App, C, F, I};
+use kalc_lib::units::{Options, Variable};
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::{Receiver, TryRecvError, channel};
+#[derive(Serialize, Deserialize)]
+pub(crate) enum Message {
+    Function(String),
+    Options(Options),
+    Vars(Vec<Variable<I, F, C>>),
+    Data(kalc_lib::units::Data<I, F, C>),
+    Close,
+}
+pub(crate) fn spawn(wake: impl Fn() + Send + 'static) -> Receiver<Message> {
+    let (send, recv) = channel();
+    std::thread::spawn(move || {
+        let mut stdin = std::io::stdin().lock();
+        loop {
+            match persist::read_frame(&mut stdin) {
+                Ok(message) => {
+                    if send.send(message).is_err() {
+                        return;
+                    }
+                    wake()
+                }
+                Err(persist::Error::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                    return;
+                }
+                Err(e @ persist::Error::Io(_)) => {
+                    eprintln!("stopped reading stdin: {e}");
+                    return;
+                }
+                Err(e) => eprintln!("ignoring malformed frame: {e}"),
+            }
+        }
+    });
+    recv
+}
+impl App {
+    pub(crate) fn poll_live(&mut self) -> (Option<String>, bool) {
+        let Some(live) = self.live.take() else {
+            return (None, false);
+        };
+        let mut function = None;
+        let mut reload = false;
+        let mut close = false;
+        loop {
+            match live.try_recv() {
+                Ok(Message::Function(f)) => function = Some(f),
+                Ok(Message::Options(mut options)) => {
+                    options.prec = options.graph_prec;
+                    self.data.options = options;
+                    reload = true;
+                }
+                Ok(Message::Vars(vars)) => {
+                    self.data.vars = vars;
+                    reload = true;
+                }
+                Ok(Message::Data(data)) => {
+                    let kalc_lib::units::Data {
+                        mut options, vars, ..
+                    } = data;
+                    options.prec = options.graph_prec;
+                    self.data.options = options;
+                    self.data.vars = vars;
+                    reload = true;
+                }
+                Ok(Message::Close) => {
+                    self.live = Some(live);
+                    close = true;
+                    break;
+                }
+                Err(TryRecvError::Empty) => {
+                    self.live = Some(live);
+                    break;
+                }
+                Err(TryRecvError::Disconnected) => break,
+            }
+        }
+        if reload {
+            self.plot.tab_complete = tab_complete(self.data.vars.clone(), self.data.options);
+        }
+        let function = function.or_else(|| reload.then(|| self.title()));
+        if let Some(function) = &function {
+            self.data.set_function(&mut self.plot, function);
+        }
+        (function, close)
+    }
+}
@@ -0,0 +1,172 @@
+#[cfg(not(feature = "rayon"))]
+use crate::IntoIter;
+use crate::data::{Coord, Data, Orbit, Plot};
+use crate::eval::Value;
+#[cfg(feature = "rayon")]
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use rupl::types::{Complex, Graph, GraphData, Vec2};
+//iterations discarded before a bifurcation column settles onto its attractor
+const TRANSIENT: usize = 256;
+//cobweb steps, and orbit points per bifurcation column, when no steps= is given
+const STEPS: usize = 32;
+const POINTS: usize = 64;
+//where an orbit starts without a ruler or a cobweb= value
+const START: f64 = 0.5;
+fn real(value: Value) -> f64 {
+    match value {
+        Value::Num(re, im) if im == 0.0 => re,
+        _ => f64::NAN,
+    }
+}
+//the first few terms of an orbit and where it ends up, for a window title where the table
+//the egui backend shows does not fit
+pub(crate) fn summary(orbit: &[f64]) -> String {
+    const SHOWN: usize = 4;
+    let term = |x: &f64| format!("{x:.6}");
+    let mut s = orbit
+        .iter()
+        .take(SHOWN)
+        .map(term)
+        .collect::<Vec<String>>()
+        .join(" → ");
+    if orbit.len() > SHOWN {
+        let last = orbit.len() - 1;
+        s.push_str(&format!(" … x_{last} = {}", term(&orbit[last])))
+    }
+    s
+}
+impl Data {
+    //x0, f(x0), f(f(x0)), ... stopping once the orbit leaves the reals
+    pub(crate) fn orbit(&self, data: &Plot, x0: f64) -> Vec<f64> {
+        let steps = data.sampling.steps.unwrap_or(STEPS);
+        let mut orbit = Vec::with_capacity(steps + 1);
+        let mut x = x0;
+        let options = self.sample_options(0);
+        while orbit.len() <= steps && x.is_finite() {
+            orbit.push(x);
+            x = real(data.eval("x", Coord::new(x, 0.0), options))
+        }
+        orbit
+    }
+    fn cobweb_start(&self, orbit: &Orbit) -> Option<f64> {
+        match orbit {
+            Orbit::Cobweb(x0) => Some(self.ruler.or(*x0).unwrap_or(START)),
+            Orbit::Bifurcation(_) => None,
+        }
+    }
+    //the orbit of every visible cobweb, for the table next to the graph
+    pub(crate) fn orbits(&self) -> Vec<(usize, Vec<f64>)> {
+        self.data
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !self.blacklist.contains(i))
+            .filter_map(|(i, data)| {
+                let data = data.as_ref()?;
+                let x0 = self.cobweb_start(data.sampling.orbit.as_ref()?)?;
+                Some((i, self.orbit(data, x0)))
+            })
+            .collect()
+    }
+    //redraws the staircases after the ruler moved, keeping the curves' view
+    pub(crate) fn update_cobwebs(&self, plot: &mut Graph) {
+        for (i, data) in self.data.iter().enumerate() {
+            let Some(data) = data else { continue };
+            let Some(orbit @ Orbit::Cobweb(_)) = &data.sampling.orbit else {
+                continue;
+            };
+            if self.blacklist.contains(&i) {
+                continue;
+            }
+            let mut buffer = plot.remove_data(i);
+            let view = match &buffer {
+                GraphData::List(list) => match list.first() {
+                    Some(GraphData::Width(v, s, e)) => Some((*s, *e, v.len().saturating_sub(1))),
+                    _ => None,
+                },
+                _ => None,
+            };
+            if let Some((s, e, len)) = view {
+                self.get_orbit(data, orbit, s, e, len, &mut buffer);
+            }
+            plot.insert_data(buffer, i)
+        }
+    }
+    //a cobweb over the curve and the identity line, or a bifurcation diagram over start..end
+    pub(crate) fn get_orbit(
+        &self,
+        data: &Plot,
+        orbit: &Orbit,
+        start: f64,
+        end: f64,
+        len: usize,
+        buffer: &mut GraphData,
+    ) -> Option<bool> {
+        match orbit {
+            Orbit::Cobweb(_) => {
+                let mut curve = GraphData::None;
+                let complex = self.get_2d(data, start, end, len, &mut curve)?;
+                let orbit = self.orbit(data, self.cobweb_start(orbit)?);
+                let mut stairs = Vec::with_capacity(2 * orbit.len());
+                if let Some(x0) = orbit.first() {
+                    stairs.push((*x0, Complex::Real(0.0)))
+                }
+                for w in orbit.windows(2) {
+                    stairs.push((w[0], Complex::Real(w[1])));
+                    stairs.push((w[1], Complex::Real(w[1])))
+                }
+                *buffer = GraphData::List(vec![
+                    curve,
+                    GraphData::Width(vec![Complex::Real(start), Complex::Real(end)], start, end),
+                    GraphData::Coord(stairs),
+                ]);
+                Some(complex)
+            }
+            Orbit::Bifurcation(_) => {
+                let points = data.sampling.steps.unwrap_or(POINTS);
+                let dr = (end - start) / len.max(1) as f64;
+                let options = self.sample_options(0);
+                let list = (0..=len)
+                    .into_par_iter()
+                    .map(|i| {
+                        let r = Coord::new(start, i as f64 * dr);
+                        let tokens = data.bind("y", r, options);
+                        let mut x = START;
+                        let mut column = Vec::with_capacity(points);
+                        for k in 0..TRANSIENT + points {
+                            x = real(tokens.eval("x", Coord::new(x, 0.0), options));
+                            if !x.is_finite() {
+                                break;
+                            }
+                            if k >= TRANSIENT {
+                                column.push(GraphData::Point(Vec2::new(r.get(), x)))
+                            }
+                        }
+                        column
+                    })
+                    .collect::<Vec<Vec<GraphData>>>()
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<GraphData>>();
+                *buffer = if list.is_empty() {
+                    GraphData::None
+                } else {
+                    GraphData::List(list)
+                };
+                Some(false)
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn summaries() {
+        assert_eq!(summary(&[0.5, 0.25]), "0.500000 → 0.250000");
+        assert_eq!(
+            summary(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]),
+            "1.000000 → 2.000000 → 3.000000 → 4.000000 … x_5 = 6.000000"
+        );
+        assert_eq!(summary(&[]), "");
+    }
+}
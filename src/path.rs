@@ -0,0 +1,163 @@
+#[cfg(not(feature = "rayon"))]
+use crate::IntoIter;
+use crate::data::{Data, Plot, point};
+use crate::sequence::substitute;
+use crate::{C, F, I};
+use kalc_lib::units::{Options, Variable};
+#[cfg(feature = "rayon")]
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use rupl::types::{Complex, GraphData};
+use std::f64::consts::{PI, TAU};
+use std::sync::atomic::{AtomicU64, Ordering};
+//points along a path before samples*k
+pub(crate) const PATH: usize = 512;
+//the winding number of a path's image around 0 from its last sampling, NaN before any
+#[derive(Debug)]
+pub(crate) struct Winding(AtomicU64);
+impl Default for Winding {
+    fn default() -> Self {
+        Self(AtomicU64::new(f64::NAN.to_bits()))
+    }
+}
+impl Clone for Winding {
+    fn clone(&self) -> Self {
+        Self(AtomicU64::new(self.0.load(Ordering::Relaxed)))
+    }
+}
+impl Winding {
+    pub(crate) fn get(&self) -> f64 {
+        f64::from_bits(self.0.load(Ordering::Relaxed))
+    }
+    fn set(&self, winding: f64) {
+        self.0.store(winding.to_bits(), Ordering::Relaxed)
+    }
+}
+//the points of segment=a..b, circle=r, circle=r@c or path=γ(t) with t from 0 to 1
+pub(crate) fn trace(
+    item: &str,
+    samples: usize,
+    options: Options,
+    vars: &[Variable<I, F, C>],
+) -> Option<Vec<(f64, f64)>> {
+    let point = |s: &str| point(s, options, vars);
+    let ts = (0..=samples).map(|i| i as f64 / samples as f64);
+    let (kind, spec) = item.split_once('=')?;
+    match kind.trim() {
+        "segment" => {
+            let (a, b) = spec.split_once("..")?;
+            let (a, b) = (point(a)?, point(b)?);
+            Some(
+                ts.map(|t| (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t))
+                    .collect(),
+            )
+        }
+        "circle" => {
+            let (r, c) = spec
+                .split_once('@')
+                .map_or((spec, None), |(r, c)| (r, Some(c)));
+            let r = point(r)?.0;
+            let c = c.map_or(Some((0.0, 0.0)), point)?;
+            Some(
+                ts.map(|t| (c.0 + r * (TAU * t).cos(), c.1 + r * (TAU * t).sin()))
+                    .collect(),
+            )
+        }
+        "path" => {
+            let points = ts
+                .map(|t| {
+                    point(&substitute(spec, "t", &format!("({t})"))).unwrap_or((f64::NAN, f64::NAN))
+                })
+                .collect::<Vec<(f64, f64)>>();
+            points
+                .iter()
+                .any(|(x, y)| x.is_finite() && y.is_finite())
+                .then_some(points)
+        }
+        _ => None,
+    }
+}
+//the turns a curve makes around 0, a whole number when it is closed
+fn winding(curve: &[(f64, Complex)]) -> f64 {
+    let point = |(x, y): &(f64, Complex)| match y {
+        Complex::Real(y) => (*x, *y),
+        _ => (f64::NAN, f64::NAN),
+    };
+    let turns = curve
+        .windows(2)
+        .map(|w| {
+            let (a, b) = (point(&w[0]), point(&w[1]));
+            let d = b.1.atan2(b.0) - a.1.atan2(a.0);
+            d - TAU * ((d + PI) / TAU).floor()
+        })
+        .filter(|d| d.is_finite())
+        .sum::<f64>()
+        / TAU;
+    match (curve.first().map(point), curve.last().map(point)) {
+        (Some(a), Some(b)) if (a.0 - b.0).hypot(a.1 - b.1) <= 1e-9 * a.0.hypot(a.1).max(1.0) => {
+            turns.round()
+        }
+        _ => turns,
+    }
+}
+//whole turns exactly, otherwise to three places
+pub(crate) fn turns(winding: f64) -> String {
+    if winding.fract() == 0.0 {
+        format!("{winding}")
+    } else {
+        format!("{winding:.3}")
+    }
+}
+impl Data {
+    //the path and its image under the function, which turns around 0 as many times as the
+    //function has zeros minus poles inside a closed path
+    pub(crate) fn get_path(
+        &self,
+        data: &Plot,
+        path: &[(f64, f64)],
+        buffer: &mut GraphData,
+    ) -> Option<bool> {
+        let image = (0..path.len())
+            .into_par_iter()
+            .map(|i| self.image(data, path[i].0, path[i].1))
+            .collect::<Vec<(f64, Complex)>>();
+        data.winding.set(winding(&image));
+        *buffer = GraphData::List(vec![
+            GraphData::Coord(path.iter().map(|(x, y)| (*x, Complex::Real(*y))).collect()),
+            GraphData::Coord(image),
+        ]);
+        Some(false)
+    }
+    //the winding number of each visible path's image, for the list next to the graph
+    pub(crate) fn windings(&self) -> Vec<(usize, f64)> {
+        self.data
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !self.blacklist.contains(i))
+            .filter_map(|(i, data)| {
+                let data = data.as_ref()?;
+                data.sampling.path.as_ref()?;
+                Some((i, data.winding.get())).filter(|(_, w)| !w.is_nan())
+            })
+            .collect()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn turns() {
+        let circle = |k: f64| {
+            (0..=64)
+                .map(|i| {
+                    let t = TAU * k * i as f64 / 64.0;
+                    (t.cos(), Complex::Real(t.sin()))
+                })
+                .collect::<Vec<(f64, Complex)>>()
+        };
+        assert_eq!(winding(&circle(1.0)), 1.0);
+        assert_eq!(winding(&circle(-2.0)), -2.0);
+        assert!((winding(&circle(0.25)) - 0.25).abs() < 1e-9);
+        assert_eq!(super::turns(-2.0), "-2");
+        assert_eq!(super::turns(0.25), "0.250");
+    }
+}
@@ -0,0 +1,130 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::fmt::{Display, Formatter};
+use std::io::Read;
+const MAGIC: [u8; 4] = *b"KPLT";
+pub(crate) const VERSION: u16 = 1;
+//index n upgrades a version n payload to version n+1,
+//version 0 is the headerless bitcode blob written before the envelope existed
+const MIGRATIONS: [fn(Vec<u8>) -> Result<Vec<u8>, Error>; VERSION as usize] = [headerless];
+#[derive(Debug)]
+pub(crate) enum Error {
+    Io(std::io::Error),
+    //neither enveloped nor readable as the headerless format
+    Magic,
+    Truncated,
+    Newer(u16),
+    Decode(u16, bitcode::Error),
+    Encode(bitcode::Error),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{e}"),
+            Error::Magic => write!(f, "data is not kalc-plot data, its header is missing"),
+            Error::Truncated => write!(f, "data ended early"),
+            Error::Newer(v) => write!(
+                f,
+                "data has schema version {v} but this kalc-plot only understands up to {VERSION}, update kalc-plot"
+            ),
+            Error::Decode(v, e) => write!(f, "schema version {v} data could not be read: {e}"),
+            Error::Encode(e) => write!(f, "could not serialize data: {e}"),
+        }
+    }
+}
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+fn headerless(payload: Vec<u8>) -> Result<Vec<u8>, Error> {
+    Ok(payload)
+}
+pub(crate) fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, Error> {
+    let payload = bitcode::serialize(value).map_err(Error::Encode)?;
+    let mut bytes = Vec::with_capacity(MAGIC.len() + 2 + payload.len());
+    bytes.extend(MAGIC);
+    bytes.extend(VERSION.to_be_bytes());
+    bytes.extend(payload);
+    Ok(bytes)
+}
+pub(crate) fn decode<T: DeserializeOwned>(bytes: Vec<u8>) -> Result<T, Error> {
+    let (version, mut payload) = split(bytes)?;
+    if version > VERSION {
+        return Err(Error::Newer(version));
+    }
+    for migrate in &MIGRATIONS[version as usize..] {
+        payload = migrate(payload)?;
+    }
+    bitcode::deserialize(&payload).map_err(|e| match version {
+        0 => Error::Magic,
+        _ => Error::Decode(version, e),
+    })
+}
+fn split(mut bytes: Vec<u8>) -> Result<(u16, Vec<u8>), Error> {
+    if !bytes.starts_with(&MAGIC) {
+        return Ok((0, bytes));
+    }
+    let header = MAGIC.len() + 2;
+    if bytes.len() < header {
+        return Err(Error::Truncated);
+    }
+    let version = u16::from_be_bytes([bytes[MAGIC.len()], bytes[MAGIC.len() + 1]]);
+    bytes.drain(..header);
+    Ok((version, bytes))
+}
+pub(crate) fn read<T: DeserializeOwned>(path: &std::path::Path) -> Result<T, Error> {
+    decode(std::fs::read(path)?)
+}
+pub(crate) fn write<T: Serialize>(path: &std::path::Path, value: &T) -> Result<(), Error> {
+    let bytes = encode(value)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, bytes)?;
+    Ok(())
+}
+pub(crate) fn read_stream<T: DeserializeOwned>(reader: &mut impl Read) -> Result<T, Error> {
+    let len = &mut [0; 8];
+    reader.read_exact(len)?;
+    let mut data = Vec::with_capacity(usize::from_be_bytes(*len).min(1 << 24));
+    reader.read_to_end(&mut data)?;
+    decode(data)
+}
+pub(crate) fn read_frame<T: DeserializeOwned>(reader: &mut impl Read) -> Result<T, Error> {
+    let len = &mut [0; 8];
+    reader.read_exact(len)?;
+    let len = u64::from_be_bytes(*len);
+    let mut data = Vec::new();
+    reader.by_ref().take(len).read_to_end(&mut data)?;
+    if data.len() as u64 != len {
+        return Err(Error::Truncated);
+    }
+    decode(data)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn envelope() {
+        let value = (1u32, "a".to_owned());
+        let bytes = encode(&value).unwrap();
+        assert_eq!(decode::<(u32, String)>(bytes.clone()).unwrap(), value);
+        let legacy = bitcode::serialize(&value).unwrap();
+        assert_eq!(decode::<(u32, String)>(legacy).unwrap(), value);
+        let mut newer = bytes;
+        newer[MAGIC.len()..MAGIC.len() + 2].copy_from_slice(&(VERSION + 1).to_be_bytes());
+        assert!(matches!(
+            decode::<(u32, String)>(newer),
+            Err(Error::Newer(_))
+        ));
+        assert!(matches!(
+            decode::<(u32, String)>(vec![0xff; 3]),
+            Err(Error::Magic)
+        ));
+        assert!(matches!(
+            decode::<(u32, String)>(MAGIC.to_vec()),
+            Err(Error::Truncated)
+        ));
+    }
+}
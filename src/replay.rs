@@ -0,0 +1,372 @@
+use crate::App;
+use crate::persist;
+use rupl::types::{InputState, Multi, Vec2};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use winit::keyboard::NamedKey;
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) enum Key {
+    Char(String),
+    Named(String),
+}
+macro_rules! named_keys {
+    ($($k:ident),*) => {
+        fn named_to_str(key: &NamedKey) -> Option<&'static str> {
+            match key {
+                $(NamedKey::$k => Some(stringify!($k)),)*
+                _ => None,
+            }
+        }
+        fn named_from_str(key: &str) -> Option<NamedKey> {
+            match key {
+                $(stringify!($k) => Some(NamedKey::$k),)*
+                _ => None,
+            }
+        }
+    };
+}
+named_keys!(
+    Escape,
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Insert,
+    Space,
+    ArrowUp,
+    ArrowDown,
+    ArrowLeft,
+    ArrowRight,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Shift,
+    Control,
+    Alt,
+    Super,
+    CapsLock,
+    NumLock,
+    ScrollLock,
+    ContextMenu,
+    PrintScreen,
+    Pause,
+    Copy,
+    Cut,
+    Paste,
+    Undo,
+    Redo,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12
+);
+impl Key {
+    fn from_winit(key: &winit::keyboard::Key) -> Option<Self> {
+        match key {
+            winit::keyboard::Key::Character(c) => Some(Key::Char(c.to_string())),
+            //keys outside the table are still recorded by name so load can refuse to replay them
+            winit::keyboard::Key::Named(n) => Some(Key::Named(
+                named_to_str(n).map_or_else(|| format!("{n:?}"), |n| n.to_owned()),
+            )),
+            _ => None,
+        }
+    }
+    fn to_winit(&self) -> Option<winit::keyboard::Key> {
+        match self {
+            Key::Char(c) => Some(winit::keyboard::Key::Character(c.as_str().into())),
+            Key::Named(n) => named_from_str(n).map(winit::keyboard::Key::Named),
+        }
+    }
+}
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub(crate) struct Frame {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) pointer: Option<bool>,
+    pub(crate) pointer_right: Option<bool>,
+    pub(crate) pointer_pos: Option<(f64, f64)>,
+    pub(crate) multi: Option<((f64, f64), f64)>,
+    pub(crate) scroll: (f64, f64),
+    pub(crate) modifiers: [bool; 4],
+    pub(crate) keys: Vec<Key>,
+    pub(crate) touches: Vec<(u64, (f64, f64))>,
+}
+impl Frame {
+    fn apply(&self, input_state: &mut InputState, touch_positions: &mut HashMap<u64, Vec2>) {
+        input_state.pointer = self.pointer;
+        input_state.pointer_right = self.pointer_right;
+        input_state.pointer_pos = self.pointer_pos.map(|(x, y)| Vec2::new(x, y));
+        input_state.multi = self.multi.map(|((x, y), zoom_delta)| Multi {
+            translation_delta: Vec2::new(x, y),
+            zoom_delta,
+        });
+        input_state.raw_scroll_delta = Vec2::new(self.scroll.0, self.scroll.1);
+        [
+            input_state.modifiers.alt,
+            input_state.modifiers.ctrl,
+            input_state.modifiers.shift,
+            input_state.modifiers.command,
+        ] = self.modifiers;
+        input_state.keys_pressed = self
+            .keys
+            .iter()
+            .filter_map(|k| k.to_winit())
+            .map(|k| k.into())
+            .collect();
+        *touch_positions = self
+            .touches
+            .iter()
+            .map(|(id, (x, y))| (*id, Vec2::new(*x, *y)))
+            .collect();
+    }
+}
+pub(crate) struct Recorder {
+    path: PathBuf,
+    frames: Vec<Frame>,
+    keys: Vec<Key>,
+}
+impl Recorder {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            frames: Vec::new(),
+            keys: Vec::new(),
+        }
+    }
+    pub(crate) fn key(&mut self, key: &winit::keyboard::Key) {
+        self.keys.extend(Key::from_winit(key))
+    }
+    pub(crate) fn frame(
+        &mut self,
+        width: u32,
+        height: u32,
+        input_state: &InputState,
+        touch_positions: &HashMap<u64, Vec2>,
+    ) {
+        let mut touches = touch_positions
+            .iter()
+            .map(|(id, p)| (*id, (p.x, p.y)))
+            .collect::<Vec<(u64, (f64, f64))>>();
+        touches.sort_by_key(|(id, _)| *id);
+        self.frames.push(Frame {
+            width,
+            height,
+            pointer: input_state.pointer,
+            pointer_right: input_state.pointer_right,
+            pointer_pos: input_state.pointer_pos.map(|p| (p.x, p.y)),
+            multi: input_state
+                .multi
+                .as_ref()
+                .map(|m| ((m.translation_delta.x, m.translation_delta.y), m.zoom_delta)),
+            scroll: (
+                input_state.raw_scroll_delta.x,
+                input_state.raw_scroll_delta.y,
+            ),
+            modifiers: [
+                input_state.modifiers.alt,
+                input_state.modifiers.ctrl,
+                input_state.modifiers.shift,
+                input_state.modifiers.command,
+            ],
+            keys: std::mem::take(&mut self.keys),
+            touches,
+        })
+    }
+    pub(crate) fn save(&self) -> Result<(), persist::Error> {
+        persist::write(&self.path, &self.frames)
+    }
+}
+//a recording with a key that cannot be pressed again would replay differently, so it is refused
+pub(crate) fn load(path: &std::path::Path) -> Result<Vec<Frame>, String> {
+    let frames: Vec<Frame> = persist::read(path).map_err(|e| e.to_string())?;
+    match frames
+        .iter()
+        .flat_map(|f| &f.keys)
+        .find(|k| k.to_winit().is_none())
+    {
+        Some(Key::Named(n) | Key::Char(n)) => Err(format!(
+            "the recording presses {n}, which cannot be replayed"
+        )),
+        None => Ok(frames),
+    }
+}
+impl App {
+    pub(crate) fn replay(&mut self, frames: &[Frame]) {
+        for frame in frames {
+            frame.apply(&mut self.input_state, &mut self.touch_positions);
+            self.touch();
+            self.prepare(frame.width, frame.height);
+            self.end_frame();
+        }
+    }
+    #[cfg(any(feature = "skia", feature = "tiny-skia"))]
+    pub(crate) fn replay_png(&mut self, frames: &[Frame]) -> Vec<u8> {
+        self.replay(frames);
+        let (width, height) = frames.last().map(|f| (f.width, f.height)).unwrap_or((0, 0));
+        self.png(width, height)
+    }
+}
+#[cfg(test)]
+#[cfg(any(feature = "skia", feature = "tiny-skia"))]
+mod tests {
+    use super::*;
+    use crate::window::touch_multi;
+    use kalc_lib::load_vars::get_vars;
+    use kalc_lib::units::Options;
+    fn app(function: &str) -> App {
+        let options = Options {
+            prec: 128,
+            graph_prec: 64,
+            graphing: true,
+            ..Options::default()
+        };
+        let mut app = App::new(
+            function.to_string(),
+            kalc_lib::units::Data {
+                vars: get_vars(options),
+                options,
+                colors: Default::default(),
+            },
+        );
+        app.plot.mult = 1.0;
+        app
+    }
+    fn frame() -> Frame {
+        Frame {
+            width: 256,
+            height: 256,
+            ..Frame::default()
+        }
+    }
+    fn drag(from: (f64, f64), to: (f64, f64), steps: usize) -> Vec<Frame> {
+        let mut frames = vec![Frame {
+            pointer: Some(true),
+            pointer_pos: Some(from),
+            ..frame()
+        }];
+        for i in 1..=steps {
+            let t = i as f64 / steps as f64;
+            frames.push(Frame {
+                pointer: Some(false),
+                pointer_pos: Some((from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t)),
+                ..frame()
+            })
+        }
+        frames.push(Frame {
+            pointer_pos: Some(to),
+            ..frame()
+        });
+        frames
+    }
+    #[test]
+    fn touch_pinch_out() {
+        let last = HashMap::from([(0, Vec2::new(90.0, 100.0)), (1, Vec2::new(110.0, 100.0))]);
+        let touch = HashMap::from([(0, Vec2::new(80.0, 100.0)), (1, Vec2::new(120.0, 100.0))]);
+        let (pos, multi) = touch_multi(&touch, &last);
+        assert_eq!((pos.x, pos.y), (100.0, 100.0));
+        assert_eq!(multi.zoom_delta, 2.0);
+        assert_eq!(
+            (multi.translation_delta.x, multi.translation_delta.y),
+            (0.0, 0.0)
+        );
+    }
+    #[test]
+    fn touch_pan() {
+        let last = HashMap::from([(0, Vec2::new(90.0, 100.0)), (1, Vec2::new(110.0, 100.0))]);
+        let touch = HashMap::from([(0, Vec2::new(95.0, 90.0)), (1, Vec2::new(115.0, 90.0))]);
+        let (pos, multi) = touch_multi(&touch, &last);
+        assert_eq!((pos.x, pos.y), (105.0, 90.0));
+        assert_eq!(multi.zoom_delta, 1.0);
+        assert_eq!(
+            (multi.translation_delta.x, multi.translation_delta.y),
+            (5.0, -10.0)
+        );
+    }
+    #[test]
+    fn touch_lift_does_not_zoom() {
+        let last = HashMap::from([(0, Vec2::new(100.0, 100.0)), (1, Vec2::new(100.0, 100.0))]);
+        let touch = HashMap::from([(0, Vec2::new(90.0, 100.0)), (1, Vec2::new(110.0, 100.0))]);
+        let (_, multi) = touch_multi(&touch, &last);
+        assert_eq!(multi.zoom_delta, 0.0);
+    }
+    #[test]
+    fn recording_roundtrip() {
+        let frames = drag((10.0, 20.0), (30.0, 40.0), 4)
+            .into_iter()
+            .chain([Frame {
+                modifiers: [false, true, true, false],
+                keys: vec![Key::Char("a".to_owned()), Key::Named("Escape".to_owned())],
+                touches: vec![(3, (1.0, 2.0))],
+                ..frame()
+            }])
+            .collect::<Vec<Frame>>();
+        let decoded: Vec<Frame> = persist::decode(persist::encode(&frames).unwrap()).unwrap();
+        assert_eq!(frames, decoded);
+    }
+    #[test]
+    fn named_keys_roundtrip() {
+        for key in [NamedKey::Escape, NamedKey::ArrowLeft, NamedKey::F12] {
+            let key = winit::keyboard::Key::Named(key);
+            assert_eq!(Key::from_winit(&key).unwrap().to_winit(), Some(key));
+        }
+    }
+    #[test]
+    fn unknown_keys_refused() {
+        let key = Key::from_winit(&winit::keyboard::Key::Named(NamedKey::MediaPlayPause)).unwrap();
+        assert_eq!(key, Key::Named("MediaPlayPause".to_owned()));
+        let path = std::env::temp_dir().join(format!("kalc-plot-keys-{}", std::process::id()));
+        let mut recorder = Recorder::new(path.clone());
+        recorder.frames = vec![
+            frame(),
+            Frame {
+                keys: vec![key],
+                ..frame()
+            },
+        ];
+        recorder.save().unwrap();
+        assert!(load(&path).unwrap_err().contains("MediaPlayPause"));
+        std::fs::remove_file(&path).unwrap();
+    }
+    #[test]
+    fn replay_is_deterministic() {
+        let frames = drag((128.0, 128.0), (64.0, 96.0), 8);
+        let a = app("sin(x)").replay_png(&frames);
+        let b = app("sin(x)").replay_png(&frames);
+        assert_eq!(a, b);
+    }
+    #[test]
+    fn drag_pans() {
+        let still = app("sin(x)").replay_png(&[frame(), frame()]);
+        let dragged = app("sin(x)").replay_png(&drag((128.0, 128.0), (64.0, 96.0), 8));
+        assert_ne!(still, dragged);
+    }
+    #[test]
+    fn two_finger_pan_matches_pan_gesture() {
+        let touch = |dx: f64| Frame {
+            touches: vec![(0, (100.0 + dx, 128.0)), (1, (140.0 + dx, 128.0))],
+            ..frame()
+        };
+        let touched = app("sin(x)").replay_png(&[frame(), touch(0.0), touch(20.0), frame()]);
+        let gesture = app("sin(x)").replay_png(&[
+            frame(),
+            frame(),
+            Frame {
+                pointer_pos: Some((140.0, 128.0)),
+                multi: Some(((20.0, 0.0), 1.0)),
+                ..frame()
+            },
+            frame(),
+        ]);
+        assert_eq!(touched, gesture);
+    }
+}
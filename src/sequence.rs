@@ -0,0 +1,322 @@
+#[cfg(not(feature = "rayon"))]
+use crate::IntoIter;
+use crate::compile::compile;
+use crate::data::{Coord, Data, Plot, constant};
+use crate::eval::{Tokens, Value};
+use crate::{C, F, I};
+use kalc_lib::units::{Options, Variable};
+#[cfg(feature = "rayon")]
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use rupl::types::{Complex, GraphData, Vec2};
+use std::sync::Mutex;
+//a recurrence is never unrolled further than this many terms past its first initial condition
+const MAX_TERMS: usize = 1 << 16;
+//beyond this f64 no longer holds every integer
+const MAX_INDEX: f64 = (1u64 << f64::MANTISSA_DIGITS) as f64;
+#[derive(Clone, Debug)]
+pub(crate) enum Sequence {
+    //a function of n, parsed with n as x
+    Explicit,
+    Recurrence(Recurrence),
+}
+//terms computed so far, starting at the first initial condition
+#[derive(Debug, Default)]
+struct Memo(Mutex<Vec<f64>>);
+impl Clone for Memo {
+    fn clone(&self) -> Self {
+        Self(Mutex::new(self.0.lock().unwrap().clone()))
+    }
+}
+//name(var)=step, name(k)=v, ...
+#[derive(Clone, Debug)]
+pub(crate) struct Recurrence {
+    //step parsed once, with var as x and each earlier term it uses as a placeholder
+    step: Tokens,
+    //each placeholder and the index of the term it stands for, parsed with var as x
+    terms: Vec<(String, Tokens)>,
+    first: isize,
+    initial: Vec<(isize, f64)>,
+    options: Options,
+    memo: Memo,
+}
+//the variable standing for the i'th earlier term a step uses, letters only so it stays one name
+fn placeholder(i: usize) -> Option<String> {
+    (i < 26).then(|| format!("recurrenceterm{}", (b'a' + i as u8) as char))
+}
+//s parsed with the names in free left as variables to bind
+fn parse(s: &str, free: &[String], options: Options, vars: &[Variable<I, F, C>]) -> Option<Tokens> {
+    let mut sumrec = free
+        .iter()
+        .map(|name| (0, name.clone()))
+        .collect::<Vec<(isize, String)>>();
+    let (func, funcvar, _, _, _) = kalc_lib::parse::input_var(
+        &format!("({s})"),
+        vars,
+        &mut sumrec,
+        &mut 0,
+        options,
+        false,
+        0,
+        Vec::new(),
+        false,
+        &mut Vec::new(),
+        None,
+        None,
+    )
+    .ok()?;
+    Some(compile(&func, &funcvar, &options).map_or(Tokens::F64(func, funcvar), Tokens::Compiled))
+}
+fn real(v: Value) -> Option<f64> {
+    match v {
+        Value::Num(re, _) => Some(re),
+        _ => None,
+    }
+}
+//the byte ranges of the runs of letters in s
+fn idents(s: &str) -> Vec<(usize, usize)> {
+    let mut runs = Vec::new();
+    let mut start = None;
+    for (i, c) in s.char_indices().chain(std::iter::once((s.len(), ' '))) {
+        match (start, c.is_alphabetic()) {
+            (None, true) => start = Some(i),
+            (Some(j), false) => {
+                runs.push((j, i));
+                start = None
+            }
+            _ => {}
+        }
+    }
+    runs
+}
+//replaces the identifier var in s by value, leaving longer names such as sin or ln alone
+pub(crate) fn substitute(s: &str, var: &str, value: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut last = 0;
+    for (start, end) in idents(s) {
+        if &s[start..end] == var {
+            out.push_str(&s[last..start]);
+            out.push_str(value);
+            last = end
+        }
+    }
+    out.push_str(&s[last..]);
+    out
+}
+pub(crate) fn mentions(s: &str, var: &str) -> bool {
+    idents(s)
+        .into_iter()
+        .any(|(start, end)| &s[start..end] == var)
+}
+//whether name reads as a sequence in n rather than a function of x or y
+pub(crate) fn explicit(name: &str) -> bool {
+    mentions(name, "n") && !["x", "y", "z"].iter().any(|v| mentions(name, v))
+}
+//the index of the bracket closing the one opened just before s
+pub(crate) fn close(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' | '{' | '[' => depth += 1,
+            ')' | '}' | ']' if depth == 0 => return Some(i),
+            ')' | '}' | ']' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+//splits s at the commas outside of any brackets
+fn items(s: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let (mut depth, mut last) = (0, 0);
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' | '{' | '[' => depth += 1,
+            ')' | '}' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(&s[last..i]);
+                last = i + 1
+            }
+            _ => {}
+        }
+    }
+    items.push(&s[last..]);
+    items
+}
+//f(arg) as f and arg
+fn call(s: &str) -> Option<(&str, &str)> {
+    let (f, arg) = s.trim().strip_suffix(')')?.split_once('(')?;
+    (!f.is_empty() && f.chars().all(char::is_alphabetic)).then_some((f, arg.trim()))
+}
+//replaces each call name(arg) in s by what value gives for arg
+fn calls(s: &str, name: &str, mut value: impl FnMut(&str) -> Option<String>) -> Option<String> {
+    let mut out = String::with_capacity(s.len());
+    let mut last = 0;
+    for (start, end) in idents(s) {
+        if start < last || &s[start..end] != name || !s[end..].starts_with('(') {
+            continue;
+        }
+        let close = end + 1 + close(&s[end + 1..])?;
+        out.push_str(&s[last..start]);
+        out.push_str(&format!("({})", value(&s[end + 1..close])?));
+        last = close + 1
+    }
+    out.push_str(&s[last..]);
+    Some(out)
+}
+impl Recurrence {
+    //a(n)=a(n-1)+a(n-2), a(0)=0, a(1)=1 and the like, None if name is not a recurrence
+    pub(crate) fn parse(name: &str, options: Options, vars: &[Variable<I, F, C>]) -> Option<Self> {
+        let items = items(name);
+        let (head, step) = items[0].split_once('=')?;
+        let (f, var) = call(head)?;
+        if !var.chars().all(char::is_alphabetic) {
+            return None;
+        }
+        let initial = items[1..]
+            .iter()
+            .map(|item| {
+                let (head, v) = item.split_once('=')?;
+                let (g, k) = call(head)?;
+                (g == f).then_some(())?;
+                Some((k.parse().ok()?, constant(v.trim(), options, vars)?))
+            })
+            .collect::<Option<Vec<(isize, f64)>>>()?;
+        let mut args = Vec::new();
+        let step = calls(step.trim(), f, |arg| {
+            let p = placeholder(args.len())?;
+            args.push((p.clone(), substitute(arg, var, "x")));
+            Some(p)
+        })?;
+        let free = args.iter().map(|(p, _)| p.clone()).collect::<Vec<String>>();
+        Some(Self {
+            step: parse(&substitute(&step, var, "x"), &free, options, vars)?,
+            terms: args
+                .into_iter()
+                .map(|(p, arg)| Some((p, parse(&arg, &[], options, vars)?)))
+                .collect::<Option<Vec<(String, Tokens)>>>()?,
+            first: initial.iter().map(|(k, _)| *k).min()?,
+            initial,
+            options,
+            memo: Memo::default(),
+        })
+    }
+    fn term(&self, k: isize, terms: &[f64]) -> f64 {
+        if let Some((_, v)) = self.initial.iter().find(|(i, _)| *i == k) {
+            return *v;
+        }
+        let n = Coord::new(k as f64, 0.0);
+        let mut step = self.step.clone();
+        for (p, index) in &self.terms {
+            let Some(v) = real(index.eval("x", n, self.options))
+                .and_then(|i| usize::try_from(i.round() as isize - self.first).ok())
+                .and_then(|i| terms.get(i).copied())
+                .filter(|v| v.is_finite())
+            else {
+                return f64::NAN;
+            };
+            step = step.bind(p, Coord::new(v, 0.0), self.options)
+        }
+        real(step.eval("x", n, self.options)).unwrap_or(f64::NAN)
+    }
+    //the terms at ks, unrolling and caching every term up to the largest one
+    fn values(&self, ks: &[f64]) -> Vec<f64> {
+        let mut memo = self.memo.0.lock().unwrap();
+        let last = ks
+            .last()
+            .map_or(self.first - 1, |k| *k as isize)
+            .min(self.first + MAX_TERMS as isize - 1);
+        while self.first + (memo.len() as isize) <= last {
+            let term = self.term(self.first + memo.len() as isize, &memo);
+            memo.push(term)
+        }
+        ks.iter()
+            .map(|k| {
+                usize::try_from(*k as isize - self.first)
+                    .ok()
+                    .and_then(|i| memo.get(i).copied())
+                    .unwrap_or(f64::NAN)
+            })
+            .collect()
+    }
+}
+impl Data {
+    //the integers between start and end as dots, or stems from the axis, thinned to at most len+1
+    pub(crate) fn get_sequence(
+        &self,
+        data: &Plot,
+        sequence: &Sequence,
+        start: f64,
+        end: f64,
+        len: usize,
+        buffer: &mut GraphData,
+    ) -> Option<bool> {
+        let (first, last) = (start.ceil().max(-MAX_INDEX), end.floor().min(MAX_INDEX));
+        if first > last || first.is_nan() || last.is_nan() {
+            *buffer = GraphData::None;
+            return Some(false);
+        }
+        let count = ((last - first) as usize).saturating_add(1);
+        let step = count.div_ceil(len + 1).max(1);
+        let ks = (0..count.div_ceil(step))
+            .map(|i| first + (i * step) as f64)
+            .collect::<Vec<f64>>();
+        let options = self.sample_options(0);
+        let values = match sequence {
+            Sequence::Explicit => ks
+                .clone()
+                .into_par_iter()
+                .map(|k| match data.eval("x", Coord::new(k, 0.0), options) {
+                    Value::Num(re, _) => re,
+                    _ => f64::NAN,
+                })
+                .collect::<Vec<f64>>(),
+            Sequence::Recurrence(recurrence) => recurrence.values(&ks),
+        };
+        let mut list = Vec::with_capacity(ks.len() * if data.sampling.stems { 2 } else { 1 });
+        for (k, v) in ks.into_iter().zip(values) {
+            if !v.is_finite() {
+                continue;
+            }
+            if data.sampling.stems {
+                list.push(GraphData::Coord(vec![
+                    (k, Complex::Real(0.0)),
+                    (k, Complex::Real(v)),
+                ]))
+            }
+            list.push(GraphData::Point(Vec2::new(k, v)))
+        }
+        *buffer = if list.is_empty() {
+            GraphData::None
+        } else {
+            GraphData::List(list)
+        };
+        Some(false)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn substitute_identifiers() {
+        assert_eq!(substitute("sin(n)+2n+ln(n)", "n", "x"), "sin(x)+2x+ln(x)");
+        assert!(explicit("sum(k,k,1,n)"));
+        assert!(!explicit("sin(x)"));
+        assert!(!explicit("n x"));
+    }
+    #[test]
+    fn replaces_calls() {
+        let mut args = Vec::new();
+        let s = calls("a(n-1)+2a(n-(2))", "a", |arg| {
+            args.push(arg.to_owned());
+            Some(args.len().to_string())
+        });
+        assert_eq!(s.as_deref(), Some("(1)+2(2)"));
+        assert_eq!(args, ["n-1", "n-(2)"]);
+        assert_eq!(placeholder(1).as_deref(), Some("recurrencetermb"));
+        assert!(placeholder(26).is_none());
+        assert_eq!(
+            items("a(n)=f(n,1), a(0)={1,2}"),
+            ["a(n)=f(n,1)", " a(0)={1,2}"]
+        );
+    }
+}
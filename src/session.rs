@@ -0,0 +1,200 @@
+use crate::App;
+use crate::data::{FunctionError, join_function};
+use crate::persist;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+const RECENT: usize = 16;
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Command {
+    Save(String),
+    Load(Option<String>),
+}
+impl Command {
+    pub(crate) fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        if let Some(name) = s.strip_prefix(":save") {
+            let name = name.trim();
+            valid(name).then(|| Self::Save(name.to_string()))
+        } else if let Some(name) = s.strip_prefix(":load") {
+            let name = name.trim();
+            if name.is_empty() {
+                Some(Self::Load(None))
+            } else {
+                valid(name).then(|| Self::Load(Some(name.to_string())))
+            }
+        } else {
+            None
+        }
+    }
+}
+impl Display for Command {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Save(name) => write!(f, ":save {name}"),
+            Self::Load(Some(name)) => write!(f, ":load {name}"),
+            Self::Load(None) => write!(f, ":load"),
+        }
+    }
+}
+fn valid(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with('.') && !name.chars().any(std::path::is_separator)
+}
+fn dir() -> std::io::Result<PathBuf> {
+    dirs::config_dir()
+        .map(|d| d.join("kalc").join("sessions"))
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no config directory"))
+}
+//the window left by the last run, restored when started without functions or a session
+fn state() -> std::io::Result<PathBuf> {
+    Ok(dir()?.with_file_name("plot"))
+}
+fn path(name: &str) -> std::io::Result<PathBuf> {
+    Ok(dir()?.join(format!("{name}.plot")))
+}
+pub(crate) fn exists(name: &str) -> bool {
+    path(name).is_ok_and(|p| p.is_file())
+}
+pub(crate) fn recent() -> Vec<String> {
+    dir()
+        .and_then(|d| std::fs::read_to_string(d.join("recent")))
+        .map(|s| {
+            s.lines()
+                .filter(|l| !l.is_empty() && exists(l))
+                .map(|l| l.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+fn push_recent(name: &str) {
+    let mut recent = recent();
+    recent.retain(|n| n != name);
+    recent.insert(0, name.to_string());
+    recent.truncate(RECENT);
+    if let Ok(dir) = dir() {
+        let _ = std::fs::write(dir.join("recent"), recent.join("\n"));
+    }
+}
+impl App {
+    pub(crate) fn save_session(&self, name: &str) -> Result<(), persist::Error> {
+        persist::write(&path(name)?, self)?;
+        push_recent(name);
+        Ok(())
+    }
+    pub(crate) fn save_state(&self) -> Result<(), persist::Error> {
+        persist::write(&state()?, self)
+    }
+    pub(crate) fn load_session(&mut self, name: &str) -> Result<(), persist::Error> {
+        let app: App = persist::read(&path(name)?)?;
+        self.apply_session(app);
+        push_recent(name);
+        Ok(())
+    }
+    fn apply_session(&mut self, app: App) {
+        let App { plot, data, .. } = app;
+        self.data = data;
+        self.data
+            .compile(&join_function(&plot.names, &plot.blacklist_graphs));
+        self.replace_plot(plot);
+        self.tiny = None;
+        #[cfg(any(feature = "skia", feature = "tiny-skia", feature = "wasm-draw"))]
+        {
+            self.name = self.title();
+        }
+    }
+    //shown with the errors, which the default backend puts in the window title
+    fn session_error(&mut self, function: String, message: String) {
+        self.data.errors.push(Some(FunctionError {
+            function,
+            message,
+            position: None,
+        }))
+    }
+    pub(crate) fn start_session(&mut self, session: Option<String>) {
+        let Some(name) = session else {
+            if self.data.data.is_empty()
+                && self.tiny.is_none()
+                && let Ok(path) = state()
+                && path.is_file()
+            {
+                match persist::read(&path) {
+                    Ok(app) => self.apply_session(app),
+                    Err(e) => self.session_error(path.display().to_string(), e.to_string()),
+                }
+            }
+            return;
+        };
+        if exists(&name)
+            && let Err(e) = self.load_session(&name)
+        {
+            self.session_error(Command::Load(Some(name.clone())).to_string(), e.to_string())
+        }
+        self.session = Some(name);
+    }
+    //runs a command typed into the functions menu once it is committed with enter or by leaving
+    //the text box, not while its name is still being typed
+    pub(crate) fn commit_session(&mut self) -> Option<String> {
+        let committed = std::mem::take(&mut self.commit) || self.plot.text_box.is_none();
+        if !committed {
+            return None;
+        }
+        let command = self.data.session.take()?;
+        self.run_session(command)
+    }
+    pub(crate) fn run_session(&mut self, command: Command) -> Option<String> {
+        for n in self.plot.names.iter_mut() {
+            if Command::parse(&n.name).is_some() {
+                n.name.clear()
+            }
+        }
+        match &command {
+            Command::Save(name) => {
+                if let Err(e) = self.save_session(name) {
+                    self.session_error(command.to_string(), e.to_string())
+                } else {
+                    self.session = Some(name.clone())
+                }
+                None
+            }
+            Command::Load(name) => {
+                let Some(name) = name.clone().or_else(|| recent().into_iter().next()) else {
+                    self.session_error(command.to_string(), "no saved sessions".to_owned());
+                    return None;
+                };
+                if let Err(e) = self.load_session(&name) {
+                    self.session_error(command.to_string(), e.to_string());
+                    None
+                } else {
+                    self.session = Some(name);
+                    Some(self.title())
+                }
+            }
+        }
+    }
+    //the recently used sessions while :load is typed without a name
+    pub(crate) fn session_hint(&self) -> Option<String> {
+        matches!(self.data.session, Some(Command::Load(None))).then(|| {
+            let recent = recent();
+            if recent.is_empty() {
+                "no saved sessions".to_owned()
+            } else {
+                format!("recent sessions: {}", recent.join(", "))
+            }
+        })
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn commands_round_trip() {
+        for command in [
+            Command::Save("a".to_owned()),
+            Command::Load(Some("b".to_owned())),
+            Command::Load(None),
+        ] {
+            assert_eq!(Command::parse(&command.to_string()), Some(command));
+        }
+        assert_eq!(Command::parse(":save ../a"), None);
+        assert_eq!(Command::parse(":save"), None);
+    }
+}
@@ -0,0 +1,273 @@
+use crate::data::{Data, Plot};
+use rupl::types::{Complex, GraphData, Vec2};
+use std::collections::HashMap;
+use std::f64::consts::{PI, TAU};
+use std::sync::Mutex;
+//newton steps taken from the centre of a cell that winds
+const NEWTON: usize = 32;
+//points on the circle a residue is integrated over
+const CONTOUR: usize = 64;
+//fraction of a cell a sample is moved by when it lands exactly on a zero or pole
+const NUDGE: f64 = 1e-3;
+//a zero or pole of a function in view
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Singularity {
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+    //the multiplicity, negative for a pole
+    pub(crate) order: isize,
+    pub(crate) residue: Option<(f64, f64)>,
+}
+//what was found on the last domain colouring grid
+#[derive(Debug, Default)]
+pub(crate) struct Found {
+    found: Mutex<Vec<Singularity>>,
+    //the samples along each axis of the grid last drawn, which its length alone does not give,
+    //written from the sampling threads
+    pub(crate) grid: Mutex<Option<(usize, usize)>>,
+}
+impl Singularity {
+    //what the list next to the graph and the window title say about it
+    pub(crate) fn label(&self) -> String {
+        let kind = if self.order > 0 { "zero" } else { "pole" };
+        let mut label = format!(
+            "{kind} of order {} at {:.6}{:+.6}i",
+            self.order.unsigned_abs(),
+            self.x,
+            self.y
+        );
+        if let Some((re, im)) = self.residue {
+            label.push_str(&format!(", residue {re:.6}{im:+.6}i"))
+        }
+        label
+    }
+}
+impl Clone for Found {
+    fn clone(&self) -> Self {
+        Self {
+            found: Mutex::new(self.found.lock().unwrap().clone()),
+            grid: Mutex::new(*self.grid.lock().unwrap()),
+        }
+    }
+}
+fn parts(c: Complex) -> (f64, f64) {
+    match c {
+        Complex::Real(re) => (re, 0.0),
+        Complex::Imag(im) => (0.0, im),
+        Complex::Complex(re, im) => (re, im),
+    }
+}
+fn div(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    let d = b.0 * b.0 + b.1 * b.1;
+    ((a.0 * b.0 + a.1 * b.1) / d, (a.1 * b.0 - a.0 * b.1) / d)
+}
+//the turns arg f makes around a loop of points, counter clockwise
+fn winding(points: &[(f64, f64)]) -> isize {
+    let mut turns = 0.0;
+    for k in 0..points.len() {
+        let (a, b) = (points[k], points[(k + 1) % points.len()]);
+        let d = b.1.atan2(b.0) - a.1.atan2(a.0);
+        turns += d - TAU * ((d + PI) / TAU).floor()
+    }
+    if turns.is_finite() {
+        (turns / TAU).round() as isize
+    } else {
+        0
+    }
+}
+impl Data {
+    //the zeros and poles on each function's grid from the last pass, left for an idle frame since
+    //refining each one costs far more than sampling it, each marked by a point on the grid
+    pub(crate) fn locate(&self, buffer: &mut [GraphData]) {
+        for (data, b) in self.data.iter().zip(buffer) {
+            let Some(data) = data else {
+                continue;
+            };
+            //markers from an earlier pass over a grid that was not resampled since come off first
+            if data.sampling.branches.is_none()
+                && let GraphData::List(list) = b
+                && matches!(list.first(), Some(GraphData::Width3D(..)))
+            {
+                let grid = list.swap_remove(0);
+                *b = grid
+            }
+            let found = self.find(data, b).unwrap_or_default();
+            if !found.is_empty() {
+                let grid = std::mem::replace(b, GraphData::None);
+                *b = GraphData::List(
+                    std::iter::once(grid)
+                        .chain(found.iter().map(|s| GraphData::Point(Vec2::new(s.x, s.y))))
+                        .collect(),
+                )
+            }
+            *data.singularities.found.lock().unwrap() = found
+        }
+    }
+    //where the argument principle puts zeros and poles on a grid from get_3d, each refined by
+    //newton's method on f, or 1/f for a pole, with its residue if it is a pole
+    fn find(&self, data: &Plot, grid: &GraphData) -> Option<Vec<Singularity>> {
+        if !self.domain
+            || data.sampling.fractal.is_some()
+            || data.sampling.sphere.is_some()
+            || data.sampling.branches.is_some()
+        {
+            return None;
+        }
+        let GraphData::Width3D(v, startx, starty, endx, endy) = grid else {
+            return None;
+        };
+        let (lenx, leny) = (*data.singularities.grid.lock().unwrap())?;
+        if v.len() != (lenx + 1) * (leny + 1) {
+            return None;
+        }
+        let (dx, dy) = ((endx - startx) / lenx as f64, (endy - starty) / leny as f64);
+        let mut found: Vec<Singularity> = Vec::new();
+        //the one found in each cell, at most one since those within a cell of another are dropped
+        let mut cells: HashMap<(isize, isize), (f64, f64)> = HashMap::new();
+        //blocks of 2x2 cells, so arg f turns at most a quarter between neighbours up to order 3
+        for j in (0..leny.saturating_sub(1)).step_by(2) {
+            for i in (0..lenx.saturating_sub(1)).step_by(2) {
+                //a zero or pole on a node has no argument and sits on the edge of every block
+                //sharing it, so f is taken a little up and right of the node, which puts it
+                //inside the block below and left only
+                let at = |(a, b): (usize, usize)| {
+                    let (i, j) = (i + a, j + b);
+                    let z = parts(v[j * (lenx + 1) + i]);
+                    if z.0.hypot(z.1) != 0.0 && z.0.is_finite() && z.1.is_finite() {
+                        return z;
+                    }
+                    self.at(
+                        data,
+                        startx + (i as f64 + NUDGE) * dx,
+                        starty + (j as f64 + NUDGE) * dy,
+                    )
+                };
+                let order = winding(
+                    &[
+                        (0, 0),
+                        (1, 0),
+                        (2, 0),
+                        (2, 1),
+                        (2, 2),
+                        (1, 2),
+                        (0, 2),
+                        (0, 1),
+                    ]
+                    .map(at),
+                );
+                if order == 0 {
+                    continue;
+                }
+                let centre = (startx + (i + 1) as f64 * dx, starty + (j + 1) as f64 * dy);
+                let (x, y) = self.newton(data, centre, order, dx, dy);
+                let cell = (
+                    ((x - startx) / dx).floor() as isize,
+                    ((y - starty) / dy).floor() as isize,
+                );
+                if (-1..=1).any(|a| {
+                    (-1..=1).any(|b| {
+                        cells
+                            .get(&(cell.0 + a, cell.1 + b))
+                            .is_some_and(|s| (s.0 - x).abs() < dx && (s.1 - y).abs() < dy)
+                    })
+                }) {
+                    continue;
+                }
+                cells.insert(cell, (x, y));
+                found.push(Singularity {
+                    x,
+                    y,
+                    order,
+                    residue: (order < 0).then(|| self.residue(data, (x, y), dx.min(dy) / 2.0)),
+                })
+            }
+        }
+        Some(found)
+    }
+    //modified newton's method, staying at the centre when it wanders out of the cell
+    fn newton(
+        &self,
+        data: &Plot,
+        centre: (f64, f64),
+        order: isize,
+        dx: f64,
+        dy: f64,
+    ) -> (f64, f64) {
+        let f = |(x, y): (f64, f64)| {
+            let v = self.at(data, x, y);
+            if order > 0 { v } else { div((1.0, 0.0), v) }
+        };
+        let h = dx.min(dy) * 1e-3;
+        let mut z = centre;
+        for _ in 0..NEWTON {
+            let v = f(z);
+            let d = f((z.0 + h, z.1));
+            let e = f((z.0 - h, z.1));
+            let derivative = ((d.0 - e.0) / (2.0 * h), (d.1 - e.1) / (2.0 * h));
+            let step = div(v, derivative);
+            let m = order.unsigned_abs() as f64;
+            let next = (z.0 - m * step.0, z.1 - m * step.1);
+            //a root of the block is at most a cell from its centre, a nudged one exactly so
+            if !next.0.is_finite()
+                || !next.1.is_finite()
+                || (next.0 - centre.0).abs() > dx * (1.0 + NUDGE)
+                || (next.1 - centre.1).abs() > dy * (1.0 + NUDGE)
+            {
+                return centre;
+            }
+            if next == z {
+                break;
+            }
+            z = next
+        }
+        z
+    }
+    //the integral of f around a circle about p over 2 pi i
+    fn residue(&self, data: &Plot, p: (f64, f64), r: f64) -> (f64, f64) {
+        let (mut re, mut im) = (0.0, 0.0);
+        for k in 0..CONTOUR {
+            let (s, c) = (TAU * k as f64 / CONTOUR as f64).sin_cos();
+            let v = self.at(data, p.0 + r * c, p.1 + r * s);
+            re += r * (v.0 * c - v.1 * s);
+            im += r * (v.0 * s + v.1 * c)
+        }
+        (re / CONTOUR as f64, im / CONTOUR as f64)
+    }
+    //the zeros and poles of each visible function, for the list next to the graph
+    pub(crate) fn singularities(&self) -> Vec<(usize, Vec<Singularity>)> {
+        self.data
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !self.blacklist.contains(i))
+            .filter_map(|(i, data)| {
+                let found = data.as_ref()?.singularities.found.lock().unwrap().clone();
+                (!found.is_empty()).then_some((i, found))
+            })
+            .collect()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn cell_winding() {
+        //z, 1/z, z^2 and z-3-3i sampled around a square about 0
+        let square = [
+            (-1.0, -1.0),
+            (0.0, -1.0),
+            (1.0, -1.0),
+            (1.0, 0.0),
+            (1.0, 1.0),
+            (0.0, 1.0),
+            (-1.0, 1.0),
+            (-1.0, 0.0),
+        ];
+        assert_eq!(winding(&square), 1);
+        assert_eq!(winding(&square.map(|z| div((1.0, 0.0), z))), -1);
+        assert_eq!(
+            winding(&square.map(|(x, y)| (x * x - y * y, 2.0 * x * y))),
+            2
+        );
+        assert_eq!(winding(&square.map(|(x, y)| (x - 3.0, y - 3.0))), 0);
+    }
+}
@@ -0,0 +1,65 @@
+#[cfg(not(feature = "rayon"))]
+use crate::IntoIter;
+use crate::data::{Data, Plot};
+#[cfg(feature = "rayon")]
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use rupl::types::{Complex, GraphData};
+use std::f64::consts::{FRAC_2_PI, FRAC_PI_2, PI};
+//how close to the north pole a sample gets, standing in for z at infinity
+const INFINITY: f64 = 1e-12;
+//f at latitude phi and longitude theta, z at the stereographic projection from the north pole
+fn value(data: &Data, plot: &Plot, phi: f64, theta: f64) -> (f64, f64) {
+    //|z| is cos(phi)/(1-sin(phi)), written so the south pole is exactly 0
+    let rho = (1.0 + phi.sin()) / phi.cos().max(INFINITY);
+    data.at(plot, rho * theta.cos(), rho * theta.sin())
+}
+//the point of the sphere at latitude phi and longitude theta, pushed out towards 2 where |f| is
+//large and in towards 1/2 where it is small, so poles and zeros stand out at any distance
+fn vertex(phi: f64, theta: f64, (re, im): (f64, f64)) -> (f64, f64, Complex) {
+    let (x, y, z) = (phi.cos() * theta.cos(), phi.cos() * theta.sin(), phi.sin());
+    let r = 2f64.powf(re.hypot(im).ln().atan() * FRAC_2_PI);
+    (r * x, r * y, Complex::Real(r * z))
+}
+impl Data {
+    //the function over the riemann sphere as rings of latitude and meridians of longitude through
+    //points of the sphere itself, whose distance from the centre carries |f|, there is no flat
+    //longitude by latitude grid to domain colour so the sphere is drawn the same in every mode
+    pub(crate) fn get_sphere(&self, data: &Plot, rings: usize) -> (GraphData, bool) {
+        let grid = (0..=rings)
+            .into_par_iter()
+            .map(|k| {
+                let phi = PI * k as f64 / rings as f64 - FRAC_PI_2;
+                (0..=2 * rings)
+                    .map(|j| {
+                        let theta = PI * j as f64 / rings as f64 - PI;
+                        (phi, theta, value(self, data, phi, theta))
+                    })
+                    .collect::<Vec<(f64, f64, (f64, f64))>>()
+            })
+            .collect::<Vec<Vec<(f64, f64, (f64, f64))>>>();
+        let complex = grid
+            .iter()
+            .flatten()
+            .any(|(_, _, (_, im))| *im != 0.0 && im.is_finite());
+        let grid = grid
+            .into_iter()
+            .map(|ring| {
+                ring.into_iter()
+                    .map(|(phi, theta, f)| vertex(phi, theta, f))
+                    .collect()
+            })
+            .collect::<Vec<Vec<(f64, f64, Complex)>>>();
+        let meridians =
+            (0..2 * rings).map(|j| GraphData::Coord3D(grid.iter().map(|ring| ring[j]).collect()));
+        (
+            GraphData::List(
+                grid[1..rings]
+                    .iter()
+                    .map(|ring| GraphData::Coord3D(ring.clone()))
+                    .chain(meridians)
+                    .collect(),
+            ),
+            complex,
+        )
+    }
+}
@@ -1,22 +1,108 @@
 #[cfg(any(feature = "skia", feature = "tiny-skia", feature = "wasm-draw"))]
 use crate::App;
 #[cfg(any(feature = "skia", feature = "tiny-skia", feature = "wasm-draw"))]
+pub(crate) fn touch_multi(
+    touch_positions: &std::collections::HashMap<u64, rupl::types::Vec2>,
+    last_touch_positions: &std::collections::HashMap<u64, rupl::types::Vec2>,
+) -> (rupl::types::Vec2, rupl::types::Multi) {
+    fn avg(vec: std::collections::hash_map::Values<u64, rupl::types::Vec2>) -> rupl::types::Vec2 {
+        let l = vec.len();
+        vec.copied().sum::<rupl::types::Vec2>() / (l as f64)
+    }
+    let cpos = avg(touch_positions.values());
+    let lpos = avg(last_touch_positions.values());
+    let cdist = touch_positions
+        .values()
+        .map(|v| (&cpos - v).norm())
+        .sum::<f64>();
+    let ldist = last_touch_positions
+        .values()
+        .map(|v| (&lpos - v).norm())
+        .sum::<f64>();
+    let zoom_delta = if ldist != 0.0 { cdist / ldist } else { 0.0 };
+    let translation_delta = cpos - lpos;
+    (
+        cpos,
+        rupl::types::Multi {
+            translation_delta,
+            zoom_delta,
+        },
+    )
+}
+#[cfg(any(feature = "skia", feature = "tiny-skia", feature = "wasm-draw"))]
 impl App {
-    #[cfg(not(target_arch = "wasm32"))]
-    pub(crate) fn set_title(&self, window: &std::sync::Arc<winit::window::Window>) {
-        if self.name.is_empty() {
-            window.set_title("kalc-plot");
+    pub(crate) fn window_title(&self) -> String {
+        let name = if self.name.is_empty() {
+            "kalc-plot"
         } else {
-            window.set_title(&self.name);
+            &self.name
+        };
+        #[cfg(feature = "kalc-lib")]
+        let name = if let Some(precision) = self.data.precision() {
+            format!("{name} [{precision}]")
+        } else {
+            name.to_owned()
+        };
+        #[cfg(feature = "bincode")]
+        #[cfg(feature = "kalc-lib")]
+        let name = if let Some(hint) = self.session_hint() {
+            format!("{name} ({hint})")
+        } else {
+            name
+        };
+        #[cfg(feature = "kalc-lib")]
+        let name = self
+            .data
+            .orbits()
+            .into_iter()
+            .fold(name, |name, (i, orbit)| {
+                format!(
+                    "{name} (orbit of {}: {})",
+                    i + 1,
+                    crate::orbit::summary(&orbit)
+                )
+            });
+        #[cfg(feature = "kalc-lib")]
+        let name = self
+            .data
+            .windings()
+            .into_iter()
+            .fold(name, |name, (i, winding)| {
+                format!(
+                    "{name} (winding of {} about 0: {})",
+                    i + 1,
+                    crate::path::turns(winding)
+                )
+            });
+        #[cfg(feature = "kalc-lib")]
+        let name = self
+            .data
+            .singularities()
+            .into_iter()
+            .flat_map(|(i, found)| found.into_iter().map(move |s| (i, s)))
+            .fold(name, |name, (i, s)| {
+                format!("{name} ({} {})", i + 1, s.label())
+            });
+        //the title cannot be coloured so the error position is underlined instead
+        #[cfg(feature = "kalc-lib")]
+        if let Some(error) = self.data.errors.iter().flatten().next() {
+            let more = self.data.errors.iter().flatten().count() - 1;
+            let more = if more > 0 {
+                format!(", {more} more")
+            } else {
+                String::new()
+            };
+            return format!("{name} ({}: {}{more})", error.marked(), error.message);
         }
+        name.to_owned()
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn set_title(&self, window: &std::sync::Arc<winit::window::Window>) {
+        window.set_title(&self.window_title());
     }
     #[cfg(target_arch = "wasm32")]
     pub(crate) fn set_title(&self, window: &winit::window::Window) {
-        if self.name.is_empty() {
-            window.set_title("kalc-plot");
-        } else {
-            window.set_title(&self.name);
-        }
+        window.set_title(&self.window_title());
     }
     #[cfg(not(feature = "skia-vulkan"))]
     #[cfg(not(feature = "wasm-draw"))]
@@ -45,6 +131,45 @@ impl App {
     pub(crate) fn window(&mut self) -> Option<&mut winit::window::Window> {
         self.window.as_mut()
     }
+    pub(crate) fn touch(&mut self) {
+        if self.touch_positions.len() > 1
+            && self.touch_positions.len() == self.last_touch_positions.len()
+        {
+            let (pos, multi) = touch_multi(&self.touch_positions, &self.last_touch_positions);
+            self.input_state.pointer_pos = Some(pos);
+            self.input_state.multi = Some(multi)
+        } else if self.touch_positions.len() == 1 {
+            self.input_state.pointer = Some(self.last_touch_positions.is_empty());
+            self.input_state.pointer_pos = self.touch_positions.values().next().copied();
+        }
+    }
+    pub(crate) fn end_frame(&mut self) {
+        self.input_state.reset();
+        self.last_touch_positions = self.touch_positions.clone();
+    }
+    fn close(&mut self) {
+        #[cfg(feature = "bincode")]
+        #[cfg(feature = "kalc-lib")]
+        if let Some(recorder) = &self.recorder
+            && let Err(e) = recorder.save()
+        {
+            eprintln!("failed to save recording: {e}")
+        }
+        #[cfg(feature = "bincode")]
+        #[cfg(feature = "kalc-lib")]
+        if let Some(name) = &self.session
+            && let Err(e) = self.save_session(name)
+        {
+            eprintln!("failed to save session {name}: {e}")
+        }
+        #[cfg(feature = "bincode")]
+        #[cfg(feature = "kalc-lib")]
+        if let Err(e) = self.save_state() {
+            eprintln!("failed to save state: {e}")
+        }
+        #[cfg(any(feature = "skia-vulkan", feature = "bincode"))]
+        self.plot.close();
+    }
     #[cfg(feature = "wasm")]
     fn get_pos(
         &self,
@@ -155,51 +280,23 @@ impl winit::application::ApplicationHandler for App {
                     let size = state.inner_size();
                     (size.width, size.height)
                 };
-                if self.touch_positions.len() > 1
-                    && self.touch_positions.len() == self.last_touch_positions.len()
-                {
-                    fn avg(
-                        vec: std::collections::hash_map::Values<u64, rupl::types::Vec2>,
-                    ) -> rupl::types::Vec2 {
-                        let l = vec.len();
-                        vec.copied().sum::<rupl::types::Vec2>() / (l as f64)
-                    }
-                    let cpos = avg(self.touch_positions.values());
-                    self.input_state.pointer_pos = Some(cpos);
-                    let lpos = avg(self.last_touch_positions.values());
-                    let cdist = self
-                        .touch_positions
-                        .values()
-                        .map(|v| (&cpos - v).norm())
-                        .sum::<f64>();
-                    let ldist = self
-                        .last_touch_positions
-                        .values()
-                        .map(|v| (&lpos - v).norm())
-                        .sum::<f64>();
-                    let zoom_delta = if ldist != 0.0 { cdist / ldist } else { 0.0 };
-                    let translation_delta = cpos - lpos;
-                    self.input_state.multi = Some(rupl::types::Multi {
-                        translation_delta,
-                        zoom_delta,
-                    })
-                } else if self.touch_positions.len() == 1 {
-                    self.input_state.pointer = Some(self.last_touch_positions.is_empty());
-                    self.input_state.pointer_pos = self.touch_positions.values().next().copied();
+                #[cfg(feature = "bincode")]
+                #[cfg(feature = "kalc-lib")]
+                if let Some(recorder) = &mut self.recorder {
+                    recorder.frame(width, height, &self.input_state, &self.touch_positions);
                 }
+                self.touch();
                 self.main(width, height);
-                if self.plot.request_redraw {
+                if self.plot.request_redraw || self.data.pending() {
                     let Some(state) = self.window() else {
                         return;
                     };
                     state.request_redraw();
                 }
-                self.input_state.reset();
-                self.last_touch_positions = self.touch_positions.clone();
+                self.end_frame();
             }
             winit::event::WindowEvent::CloseRequested => {
-                #[cfg(any(feature = "skia-vulkan", feature = "bincode"))]
-                self.plot.close();
+                self.close();
                 event_loop.exit();
             }
             winit::event::WindowEvent::KeyboardInput { event, .. } => {
@@ -208,6 +305,18 @@ impl winit::application::ApplicationHandler for App {
                         return;
                     };
                     state.request_redraw();
+                    #[cfg(feature = "bincode")]
+                    #[cfg(feature = "kalc-lib")]
+                    if let Some(recorder) = &mut self.recorder {
+                        recorder.key(&event.logical_key);
+                    }
+                    #[cfg(feature = "bincode")]
+                    #[cfg(feature = "kalc-lib")]
+                    if event.logical_key
+                        == winit::keyboard::Key::Named(winit::keyboard::NamedKey::Enter)
+                    {
+                        self.commit = true
+                    }
                     self.input_state.keys_pressed.push(event.logical_key.into());
                 }
             }
@@ -341,6 +450,30 @@ impl winit::application::ApplicationHandler for App {
             _ => {}
         }
     }
+    #[cfg(feature = "kalc-lib")]
+    #[cfg(not(target_arch = "wasm32"))]
+    fn user_event(&mut self, event_loop: &winit::event_loop::ActiveEventLoop, _: ()) {
+        #[cfg(feature = "bincode")]
+        let (name, close) = self.poll_live();
+        #[cfg(not(feature = "bincode"))]
+        let (name, close) = (None, false);
+        let (control_name, control_close) = self.poll_control();
+        let (name, close) = (control_name.or(name), close || control_close);
+        if close {
+            self.close();
+            event_loop.exit();
+            return;
+        }
+        if let Some(name) = name {
+            self.name = name;
+            if let Some(w) = self.window().cloned() {
+                self.set_title(&w);
+            }
+        }
+        if let Some(w) = self.window() {
+            w.request_redraw();
+        }
+    }
     fn suspended(&mut self, _: &winit::event_loop::ActiveEventLoop) {
         #[cfg(not(feature = "skia-vulkan"))]
         #[cfg(not(feature = "wasm-draw"))]